@@ -0,0 +1,221 @@
+use crate::coupling::dir_of;
+use crate::MyDataFrame;
+use config::BusFactorOptions;
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+/// 整个repo的汇总行的dir
+const ALL_DIRS: &str = "*";
+
+/// 按改动行数从多到少，累计占比达到`threshold`所需的最少作者数
+fn bus_factor(churn_desc: &[i64], threshold: f64) -> u32 {
+    let total: i64 = churn_desc.iter().sum();
+    let mut acc = 0;
+    for (i, churn) in churn_desc.iter().enumerate() {
+        acc += churn;
+        if acc as f64 >= total as f64 * threshold {
+            return i as u32 + 1;
+        }
+    }
+    churn_desc.len() as u32
+}
+
+#[derive(Default)]
+struct Rows {
+    repo: Vec<String>,
+    dir: Vec<String>,
+    authors: Vec<u32>,
+    churn: Vec<i64>,
+    bus_factor: Vec<u32>,
+    owner: Vec<String>,
+    owner_share: Vec<f64>,
+}
+
+impl Rows {
+    fn push(
+        &mut self,
+        (repo, dir): &(String, String),
+        by_author: &HashMap<String, i64>,
+        threshold: f64,
+    ) {
+        let mut authors: Vec<(&String, i64)> = by_author.iter().map(|(a, c)| (a, *c)).collect();
+        // 改动多的在前，相同时按名字，保证结果稳定
+        authors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let total: i64 = authors.iter().map(|(_, c)| c).sum();
+        if total == 0 {
+            return;
+        }
+        let churn: Vec<i64> = authors.iter().map(|(_, c)| *c).collect();
+        self.repo.push(repo.clone());
+        self.dir.push(dir.clone());
+        self.authors.push(authors.len() as u32);
+        self.churn.push(total);
+        self.bus_factor.push(bus_factor(&churn, threshold));
+        self.owner.push(authors[0].0.clone());
+        self.owner_share.push(authors[0].1 as f64 / total as f64);
+    }
+
+    fn into_df(self) -> DataFrame {
+        DataFrame::new(vec![
+            Series::new("repo", self.repo),
+            Series::new("dir", self.dir),
+            Series::new("authors", self.authors),
+            Series::new("churn", self.churn),
+            Series::new("bus_factor", self.bus_factor),
+            Series::new("owner", self.owner),
+            Series::new("owner_share", self.owner_share),
+        ])
+        .unwrap()
+    }
+}
+
+impl MyDataFrame<'_> {
+    /// 知识集中度：每个repo和目录按作者改动行数占比计算bus factor，
+    /// 主要owner在统计窗口结束前超过`inactive_days`天没有commit的标记为`at_risk`
+    ///
+    /// `dir` 为 `*` 的是整个repo的汇总
+    pub fn bus_factor(&self, options: &BusFactorOptions) -> Result<DataFrame, String> {
+        let files = self
+            .filtered_files()?
+            .unique(
+                Some(vec![
+                    "repo".to_string(),
+                    "commit_id".to_string(),
+                    "path".to_string(),
+                ]),
+                UniqueKeepStrategy::First,
+            )
+            .select([
+                col("repo"),
+                col("path"),
+                col("author"),
+                (col("insertions") + col("deletions"))
+                    .cast(DataType::Int64)
+                    .alias("churn"),
+            ])
+            .collect()
+            .unwrap();
+
+        // (repo, dir) => author => 改动行数
+        let mut ownership: BTreeMap<(String, String), HashMap<String, i64>> = BTreeMap::new();
+        let repos = files.column("repo").unwrap().str().unwrap();
+        let paths = files.column("path").unwrap().str().unwrap();
+        let authors = files.column("author").unwrap().str().unwrap();
+        let churns = files.column("churn").unwrap().i64().unwrap();
+        for (((repo, path), author), churn) in repos.into_iter().zip(paths).zip(authors).zip(churns)
+        {
+            let (Some(repo), Some(path), Some(author), Some(churn)) = (repo, path, author, churn)
+            else {
+                continue;
+            };
+            for dir in [ALL_DIRS.to_string(), dir_of(path, Some(options.dir_depth))] {
+                *ownership
+                    .entry((repo.to_string(), dir))
+                    .or_default()
+                    .entry(author.to_string())
+                    .or_default() += churn;
+            }
+        }
+        let mut rows = Rows::default();
+        for (key, by_author) in &ownership {
+            rows.push(key, by_author, options.threshold);
+        }
+
+        // 作者最后一次commit距离统计窗口结束(--until，否则最后一个commit)的天数
+        let window_end = match self.filter_options.until {
+            Some(until) => lit(until.naive_utc()),
+            None => col("owner_last_commit").max(),
+        };
+        let activity = self
+            .filtered()
+            .group_by([col("author").alias("owner")])
+            .agg([col("date").max().alias("owner_last_commit")])
+            .with_columns([
+                (window_end - col("owner_last_commit"))
+                    .dt()
+                    .total_days()
+                    .alias("owner_idle_days"),
+                self.report_time("owner_last_commit"),
+            ]);
+
+        Ok(rows
+            .into_df()
+            .lazy()
+            .join(
+                activity,
+                [col("owner")],
+                [col("owner")],
+                JoinArgs::new(JoinType::Left),
+            )
+            .with_column(
+                col("owner_idle_days")
+                    .gt(lit(options.inactive_days))
+                    .alias("at_risk"),
+            )
+            .sort(
+                ["at_risk", "bus_factor", "churn"],
+                SortMultipleOptions::default()
+                    .with_order_descending_multi([true, false, true])
+                    .with_maintain_order(true),
+            )
+            .collect()
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{detail_df, files_df, filter_options};
+
+    #[test]
+    fn test_bus_factor() {
+        assert_eq!(bus_factor(&[6, 3, 1], 0.5), 1);
+        assert_eq!(bus_factor(&[4, 4, 2], 0.5), 2);
+        assert_eq!(bus_factor(&[4, 4, 2], 0.9), 3);
+        assert_eq!(bus_factor(&[1, 1, 1, 1], 1.0), 4);
+    }
+
+    #[test]
+    fn test_bus_factor_report() {
+        let df = detail_df();
+        let files = files_df();
+        let opts = filter_options(false);
+        let options = BusFactorOptions {
+            threshold: 0.5,
+            inactive_days: 10,
+            dir_depth: 1,
+        };
+        let report = MyDataFrame::new(&df, &opts)
+            .with_files(&files)
+            .bus_factor(&options)
+            .unwrap();
+        // api: *, api, . + api-fork: *, api
+        assert_eq!(report.height(), 5);
+
+        let row = |repo: &str, dir: &str| {
+            let repos = report.column("repo").unwrap().str().unwrap();
+            let dirs = report.column("dir").unwrap().str().unwrap();
+            repos
+                .into_iter()
+                .zip(dirs)
+                .position(|(r, d)| r == Some(repo) && d == Some(dir))
+                .unwrap()
+        };
+        let owner = report.column("owner").unwrap().str().unwrap();
+        let share = report.column("owner_share").unwrap().f64().unwrap();
+        let at_risk = report.column("at_risk").unwrap().bool().unwrap();
+        // api: peter 12 lines (c1 + c3), bob 10 lines (c4)
+        let api = row("api", ALL_DIRS);
+        assert_eq!(owner.get(api), Some("peter"));
+        assert_eq!(share.get(api), Some(12.0 / 22.0));
+        assert_eq!(at_risk.get(api), Some(false));
+        // bob's last commit is 2024-02-03, window ends 2024-03-01
+        let fork = row("api-fork", "api");
+        assert_eq!(owner.get(fork), Some("bob"));
+        assert_eq!(at_risk.get(fork), Some(true));
+        let idle = report.column("owner_idle_days").unwrap().i64().unwrap();
+        assert_eq!(idle.get(fork), Some(27));
+        assert_eq!(at_risk.get(0), Some(true));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::MyDataFrame;
+use config::CouplingOptions;
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+/// 文件所在目录，`depth` 限制目录层数，根目录下的文件为 `.`
+pub(crate) fn dir_of(path: &str, depth: Option<usize>) -> String {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    parts.pop();
+    if let Some(depth) = depth {
+        parts.truncate(depth);
+    }
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    /// (repo, entity) => 变更的commit数
+    changes: HashMap<(String, String), u32>,
+    /// (repo, a, b) => 一起变更的commit数，a < b
+    co_changes: HashMap<(String, String, String), u32>,
+}
+
+impl Counter {
+    fn add(&mut self, repo: &str, entities: &BTreeSet<String>) {
+        for e in entities {
+            *self
+                .changes
+                .entry((repo.to_string(), e.clone()))
+                .or_default() += 1;
+        }
+        let entities: Vec<&String> = entities.iter().collect();
+        for (i, a) in entities.iter().enumerate() {
+            for b in &entities[i + 1..] {
+                *self
+                    .co_changes
+                    .entry((repo.to_string(), a.to_string(), b.to_string()))
+                    .or_default() += 1;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Rows {
+    repo: Vec<String>,
+    level: Vec<&'static str>,
+    entity_a: Vec<String>,
+    entity_b: Vec<String>,
+    co_changes: Vec<u32>,
+    commits_a: Vec<u32>,
+    commits_b: Vec<u32>,
+    degree: Vec<f64>,
+}
+
+impl Rows {
+    fn extend(&mut self, level: &'static str, counter: &Counter, options: &CouplingOptions) {
+        for ((repo, a, b), co) in &counter.co_changes {
+            let ca = counter.changes[&(repo.clone(), a.clone())];
+            let cb = counter.changes[&(repo.clone(), b.clone())];
+            // 同code-maat: 共同变更次数 / 两者平均变更次数
+            let degree = *co as f64 / ((ca + cb) as f64 / 2.0);
+            if *co < options.min_co_changes
+                || ca.min(cb) < options.min_commits
+                || degree < options.min_degree
+            {
+                continue;
+            }
+            self.repo.push(repo.clone());
+            self.level.push(level);
+            self.entity_a.push(a.clone());
+            self.entity_b.push(b.clone());
+            self.co_changes.push(*co);
+            self.commits_a.push(ca);
+            self.commits_b.push(cb);
+            self.degree.push(degree);
+        }
+    }
+
+    fn into_df(self) -> DataFrame {
+        DataFrame::new(vec![
+            Series::new("repo", self.repo),
+            Series::new("level", self.level),
+            Series::new("entity_a", self.entity_a),
+            Series::new("entity_b", self.entity_b),
+            Series::new("co_changes", self.co_changes),
+            Series::new("commits_a", self.commits_a),
+            Series::new("commits_b", self.commits_b),
+            Series::new("degree", self.degree),
+        ])
+        .unwrap()
+    }
+}
+
+impl MyDataFrame<'_> {
+    /// 变更耦合：经常在同一个commit中一起变更的文件对和目录对
+    pub fn coupling(&self, options: &CouplingOptions) -> Result<DataFrame, String> {
+        let df = self
+            .filtered_files()?
+            .select([col("repo"), col("commit_id"), col("path")])
+            .unique(None, UniqueKeepStrategy::First)
+            .collect()
+            .unwrap();
+
+        // (repo, commit) => 变更的文件
+        let mut changesets: HashMap<(&str, &str), BTreeSet<String>> = HashMap::new();
+        let repos = df.column("repo").unwrap().str().unwrap();
+        let commits = df.column("commit_id").unwrap().str().unwrap();
+        let paths = df.column("path").unwrap().str().unwrap();
+        for ((repo, commit), path) in repos.into_iter().zip(commits).zip(paths) {
+            let (Some(repo), Some(commit), Some(path)) = (repo, commit, path) else {
+                continue;
+            };
+            changesets
+                .entry((repo, commit))
+                .or_default()
+                .insert(path.to_string());
+        }
+
+        let mut files = Counter::default();
+        let mut dirs = Counter::default();
+        for ((repo, _), paths) in &changesets {
+            // 大的commit（导入、格式化）会制造大量噪音耦合
+            if paths.len() > options.max_files {
+                continue;
+            }
+            files.add(repo, paths);
+            let dir_set = paths
+                .iter()
+                .map(|p| dir_of(p, options.dir_depth))
+                .collect::<BTreeSet<_>>();
+            dirs.add(repo, &dir_set);
+        }
+
+        let mut rows = Rows::default();
+        rows.extend("file", &files, options);
+        rows.extend("dir", &dirs, options);
+        Ok(rows
+            .into_df()
+            .sort(
+                ["degree", "co_changes", "entity_a", "entity_b"],
+                SortMultipleOptions::default()
+                    .with_order_descending_multi([true, true, false, false]),
+            )
+            .unwrap())
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// coupling报表转成Graphviz DOT，一个repo一个cluster，边的粗细表示耦合度
+pub fn to_dot(df: &DataFrame) -> Result<String, Box<dyn Error>> {
+    let repo = df.column("repo")?.str()?;
+    let level = df.column("level")?.str()?;
+    let a = df.column("entity_a")?.str()?;
+    let b = df.column("entity_b")?.str()?;
+    let co = df.column("co_changes")?.cast(&DataType::UInt32)?;
+    let co = co.u32()?;
+    let degree = df.column("degree")?.f64()?;
+
+    let mut clusters: Vec<(String, Vec<String>)> = vec![];
+    for i in 0..df.height() {
+        let (Some(repo), Some(level), Some(a), Some(b)) =
+            (repo.get(i), level.get(i), a.get(i), b.get(i))
+        else {
+            continue;
+        };
+        let degree = degree.get(i).unwrap_or(0.0);
+        let edge = format!(
+            "    \"{repo}:{level}:{a}\" -- \"{repo}:{level}:{b}\" [label=\"{co}\", penwidth={w:.2}];",
+            repo = dot_escape(repo),
+            a = dot_escape(a),
+            b = dot_escape(b),
+            co = co.get(i).unwrap_or(0),
+            w = 1.0 + degree * 4.0,
+        );
+        match clusters.iter_mut().find(|(r, _)| r == repo) {
+            Some((_, edges)) => edges.push(edge),
+            None => clusters.push((repo.to_string(), vec![edge])),
+        }
+    }
+
+    let mut dot = String::from("graph coupling {\n  node [shape=box];\n");
+    for (i, (repo, edges)) in clusters.iter().enumerate() {
+        dot.push_str(&format!(
+            "  subgraph cluster_{} {{\n    label=\"{}\";\n",
+            i,
+            dot_escape(repo)
+        ));
+        for edge in edges {
+            dot.push_str(edge);
+            dot.push('\n');
+        }
+        dot.push_str("  }\n");
+    }
+    dot.push_str("}\n");
+    Ok(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{detail_df, files_df, filter_options};
+
+    fn options() -> CouplingOptions {
+        CouplingOptions {
+            min_co_changes: 1,
+            min_commits: 1,
+            min_degree: 0.0,
+            max_files: 10,
+            dir_depth: None,
+        }
+    }
+
+    #[test]
+    fn test_dir_of() {
+        assert_eq!(dir_of("README.md", None), ".");
+        assert_eq!(dir_of("crates/git/commit.rs", None), "crates/git");
+        assert_eq!(dir_of("crates/git/commit.rs", Some(1)), "crates");
+    }
+
+    #[test]
+    fn test_coupling() {
+        let df = detail_df();
+        let files = files_df();
+        let opts = filter_options(false);
+        let coupling = MyDataFrame::new(&df, &opts)
+            .with_files(&files)
+            .coupling(&options())
+            .unwrap();
+        // user.go + user_test.go in api (2 commits) and api-fork (1 commit), same dir => no dir pair
+        assert_eq!(coupling.height(), 2);
+        let levels = coupling.column("level").unwrap().str().unwrap();
+        assert!(levels.into_iter().all(|l| l == Some("file")));
+        let co = coupling.column("co_changes").unwrap().u32().unwrap();
+        assert_eq!(co.get(0), Some(2));
+        let degree = coupling.column("degree").unwrap().f64().unwrap();
+        assert_eq!(degree.get(0), Some(1.0));
+
+        let mut strict = options();
+        strict.min_co_changes = 2;
+        let coupling = MyDataFrame::new(&df, &opts)
+            .with_files(&files)
+            .coupling(&strict)
+            .unwrap();
+        assert_eq!(coupling.height(), 1);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let df = detail_df();
+        let files = files_df();
+        let opts = filter_options(false);
+        let coupling = MyDataFrame::new(&df, &opts)
+            .with_files(&files)
+            .coupling(&options())
+            .unwrap();
+        let dot = to_dot(&coupling).unwrap();
+        assert!(dot.starts_with("graph coupling {"));
+        assert!(dot.contains("\"api:file:api/user.go\" -- \"api:file:api/user_test.go\""));
+        assert_eq!(dot.matches("subgraph").count(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::MyDataFrame;
+use config::BusinessHours;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// 工作时间以外的commit，`local` 是作者时区的本地时间
+pub fn outside_hours(local: Expr, business_hours: &BusinessHours) -> Expr {
+    let weekday = local.clone().dt().weekday();
+    let hour = local.dt().hour();
+    let workday = business_hours.workdays.iter().fold(lit(false), |acc, d| {
+        acc.or(weekday.clone().eq(lit(*d as i32)))
+    });
+    let inside = workday
+        .and(hour.clone().gt_eq(lit(business_hours.start as i32)))
+        .and(hour.lt(lit(business_hours.end as i32)));
+    inside.not()
+}
+
+impl MyDataFrame<'_> {
+    /// commit在作者自己时区的本地时间，老的detail数据没有tz_offset时按UTC
+    pub fn author_local_date(&self) -> Expr {
+        if !self.has_column("tz_offset") {
+            return col("date");
+        }
+        col("date") + duration(DurationArgs::new().with_minutes(col("tz_offset")))
+    }
+
+    /// 每个作者按 星期 x 小时 统计commit数量，一行一个(author, weekday)，24个小时列 `00`-`23`(表格只按这些列着色)和total
+    pub fn heatmap(&self) -> DataFrame {
+        let local = self.author_local_date();
+        let df = self
+            .filtered()
+            .select([
+                col("author"),
+                local.clone().dt().weekday().alias("weekday"),
+                local.dt().hour().alias("hour"),
+            ])
+            .collect()
+            .unwrap();
+
+        let mut counts: BTreeMap<String, [[u32; 24]; 7]> = BTreeMap::new();
+        let authors = df.column("author").unwrap().str().unwrap();
+        let weekdays = df
+            .column("weekday")
+            .unwrap()
+            .cast(&DataType::Int32)
+            .unwrap();
+        let hours = df.column("hour").unwrap().cast(&DataType::Int32).unwrap();
+        for ((author, weekday), hour) in authors
+            .into_iter()
+            .zip(weekdays.i32().unwrap())
+            .zip(hours.i32().unwrap())
+        {
+            let (Some(author), Some(weekday), Some(hour)) = (author, weekday, hour) else {
+                continue;
+            };
+            let grid = counts.entry(author.to_string()).or_insert([[0; 24]; 7]);
+            grid[(weekday - 1) as usize][hour as usize] += 1;
+        }
+
+        let mut author_col: Vec<String> = vec![];
+        let mut weekday_col: Vec<&str> = vec![];
+        let mut hour_cols: Vec<Vec<u32>> = vec![vec![]; 24];
+        let mut total_col: Vec<u32> = vec![];
+        for (author, grid) in &counts {
+            for (d, row) in grid.iter().enumerate() {
+                author_col.push(author.clone());
+                weekday_col.push(WEEKDAYS[d]);
+                for (h, count) in row.iter().enumerate() {
+                    hour_cols[h].push(*count);
+                }
+                total_col.push(row.iter().sum());
+            }
+        }
+
+        let mut columns = vec![
+            Series::new("author", author_col),
+            Series::new("weekday", weekday_col),
+        ];
+        for (h, values) in hour_cols.into_iter().enumerate() {
+            columns.push(Series::new(&format!("{:02}", h), values));
+        }
+        columns.push(Series::new("total", total_col));
+        DataFrame::new(columns).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{detail_df, filter_options};
+    use crate::MyDataFrame;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_heatmap() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        let heatmap = MyDataFrame::new(&df, &opts).heatmap();
+        // 7 weekdays per author
+        assert_eq!(heatmap.height(), 14);
+        assert_eq!(heatmap.width(), 2 + 24 + 1);
+
+        let peter = heatmap
+            .clone()
+            .lazy()
+            .filter(
+                col("author")
+                    .eq(lit("peter"))
+                    .and(col("weekday").eq(lit("Thu"))),
+            )
+            .collect()
+            .unwrap();
+        // 2024-02-01 10:00 UTC at +08:00 is Thursday 18:00 local
+        assert_eq!(peter.column("18").unwrap().u32().unwrap().get(0), Some(1));
+        assert_eq!(
+            peter.column("total").unwrap().u32().unwrap().get(0),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_outside_hours_share() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        let summ = MyDataFrame::new(&df, &opts)
+            .summary()
+            .lazy()
+            .filter(col("author").eq(lit("peter")))
+            .collect()
+            .unwrap();
+        // both of peter's commits are at 18:00 local, business hours end at 18
+        let share = summ.column("outside_hours_share").unwrap().f64().unwrap();
+        assert!(share.into_iter().all(|v| v == Some(1.0)));
+    }
+}
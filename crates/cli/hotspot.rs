@@ -0,0 +1,129 @@
+use crate::MyDataFrame;
+use config::HotspotWeights;
+use polars::prelude::*;
+
+/// 按最大值归一化到0~1，全为0时为0
+fn normalize(e: Expr) -> Expr {
+    let max = e.clone().max();
+    when(max.clone().gt(lit(0)))
+        .then(e.cast(DataType::Float64) / max.cast(DataType::Float64))
+        .otherwise(lit(0.0))
+}
+
+impl MyDataFrame<'_> {
+    /// 文件热点：commit数、作者数、变更行数、最近变更时间，加权合成hotspot分数
+    ///
+    /// 同一个commit出现在多个分支只算一次，所以按repo+path统计，不区分branch
+    pub fn hotspot(&self, weights: &HotspotWeights) -> Result<DataFrame, String> {
+        let files = self
+            .filtered_files()?
+            .unique(
+                Some(vec![
+                    "repo".to_string(),
+                    "commit_id".to_string(),
+                    "path".to_string(),
+                ]),
+                UniqueKeepStrategy::First,
+            )
+            .group_by([col("repo"), col("path")])
+            .agg([
+                col("category").first(),
+                col("commit_id").n_unique().alias("commits"),
+                col("author").n_unique().alias("authors"),
+                (col("insertions") + col("deletions")).sum().alias("churn"),
+                col("date").max().alias("last_changed"),
+            ]);
+
+        // 距离统计窗口结束(--until，否则最后一次变更)的天数
+        let window_end = match self.filter_options.until {
+            Some(until) => lit(until.naive_utc()),
+            None => col("last_changed").max(),
+        };
+        let age_days = (window_end - col("last_changed")).dt().total_days();
+        let recency = lit(1.0) - normalize(age_days.clone());
+
+        let score = lit(weights.commits) * normalize(col("commits"))
+            + lit(weights.authors) * normalize(col("authors"))
+            + lit(weights.churn) * normalize(col("churn"))
+            + lit(weights.recency) * recency;
+
+        Ok(files
+            .with_columns([
+                age_days.alias("age_days"),
+                score.alias("score"),
+                self.report_time("last_changed"),
+            ])
+            .sort(
+                ["score", "churn"],
+                SortMultipleOptions::default()
+                    .with_order_descending(true)
+                    .with_maintain_order(true),
+            )
+            .collect()
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{detail_df, files_df, filter_options};
+    use crate::MyDataFrame;
+    use config::HotspotWeights;
+
+    #[test]
+    fn test_hotspot() {
+        let df = detail_df();
+        let files = files_df();
+        let opts = filter_options(false);
+        let hotspot = MyDataFrame::new(&df, &opts)
+            .with_files(&files)
+            .hotspot(&HotspotWeights::default())
+            .unwrap();
+
+        let paths = hotspot.column("path").unwrap().str().unwrap();
+        assert_eq!(paths.get(0), Some("api/user.go"));
+        let row = hotspot
+            .column("path")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .position(|p| p == Some("api/user.go"))
+            .unwrap();
+        // c1 and c3 (same repo) + c2 (fork)
+        let repos = hotspot.column("repo").unwrap().str().unwrap();
+        assert_eq!(repos.get(row), Some("api"));
+        let commits = hotspot.column("commits").unwrap().u32().unwrap();
+        assert_eq!(commits.get(row), Some(2));
+        let churn = hotspot.column("churn").unwrap().i64().unwrap();
+        assert_eq!(churn.get(row), Some(8));
+
+        // no files detail
+        let err = MyDataFrame::new(&df, &opts)
+            .hotspot(&HotspotWeights::default())
+            .unwrap_err();
+        assert!(err.starts_with("no files detail"));
+    }
+
+    #[test]
+    fn test_hotspot_weights() {
+        let df = detail_df();
+        let files = files_df();
+        let opts = filter_options(false);
+        // only churn counts
+        let weights = HotspotWeights {
+            commits: 0.0,
+            authors: 0.0,
+            churn: 1.0,
+            recency: 0.0,
+        };
+        let hotspot = MyDataFrame::new(&df, &opts)
+            .with_files(&files)
+            .hotspot(&weights)
+            .unwrap();
+        let paths = hotspot.column("path").unwrap().str().unwrap();
+        assert_eq!(paths.get(0), Some("README.md"));
+        let score = hotspot.column("score").unwrap().f64().unwrap();
+        assert_eq!(score.get(0), Some(1.0));
+    }
+}
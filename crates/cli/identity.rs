@@ -0,0 +1,179 @@
+use chrono::NaiveDate;
+use config::{Alias, Author, MatchKind};
+use polars::prelude::*;
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Name(String),
+    /// 小写
+    Email(String),
+    /// 小写
+    EmailDomain(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    author: String,
+    matcher: Matcher,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+}
+
+/// 机器人账号的commit归到这个作者
+pub const BOTS: &str = "(bots)";
+
+/// 作者身份解析：commit的(名字, email, 日期)对应到配置的作者名，只在这里匹配一次
+///
+/// 机器人规则最先匹配，其余按精确程度排序：email、alias名字、作者名本身、正则、email域名，同一类按配置顺序，第一个匹配的生效
+#[derive(Debug, Clone, Default)]
+pub struct IdentityResolver {
+    rules: Vec<Rule>,
+}
+
+impl IdentityResolver {
+    /// 配置已经检查过，正则一定能编译
+    pub fn new(authors: &[Author], bots: &[Alias]) -> Self {
+        let rule = |rank: Option<u8>, author: &str, alias: &Alias| {
+            let (kind, value) = alias.matcher().ok()?;
+            let (kind_rank, matcher) = match kind {
+                MatchKind::Email => (1, Matcher::Email(value.to_lowercase())),
+                MatchKind::Name => (2, Matcher::Name(value.to_string())),
+                MatchKind::Regex => (4, Matcher::Regex(Regex::new(value).unwrap())),
+                MatchKind::EmailDomain => (5, Matcher::EmailDomain(value.to_lowercase())),
+            };
+            let (since, until) = alias.dates();
+            Some((
+                rank.unwrap_or(kind_rank),
+                Rule {
+                    author: author.to_string(),
+                    matcher,
+                    since,
+                    until,
+                },
+            ))
+        };
+        let mut ranked: Vec<(u8, Rule)> = bots
+            .iter()
+            .filter_map(|bot| rule(Some(0), BOTS, bot))
+            .collect();
+        for author in authors {
+            for alias in &author.alias {
+                ranked.extend(rule(None, &author.name, alias));
+            }
+            ranked.push((
+                3,
+                Rule {
+                    author: author.name.clone(),
+                    matcher: Matcher::Name(author.name.clone()),
+                    since: None,
+                    until: None,
+                },
+            ));
+        }
+        ranked.sort_by_key(|(rank, _)| *rank);
+        IdentityResolver {
+            rules: ranked.into_iter().map(|(_, rule)| rule).collect(),
+        }
+    }
+
+    /// 没有日期时不检查规则的日期范围
+    pub fn resolve(&self, name: &str, email: &str, date: Option<NaiveDate>) -> Option<&str> {
+        let email = email.to_lowercase();
+        let domain = email.rsplit_once('@').map(|(_, d)| d).unwrap_or("");
+        self.rules
+            .iter()
+            .filter(|r| match date {
+                Some(date) => {
+                    r.since.is_none_or(|since| since <= date)
+                        && r.until.is_none_or(|until| date <= until)
+                }
+                None => true,
+            })
+            .find(|r| match &r.matcher {
+                Matcher::Name(n) => n == name,
+                Matcher::Email(e) => !email.is_empty() && *e == email,
+                Matcher::EmailDomain(d) => d == domain,
+                Matcher::Regex(re) => re.is_match(name),
+            })
+            .map(|r| r.author.as_str())
+    }
+
+    /// `name`、`email`、`date` 列逐行解析出的作者名，没有匹配的为null
+    pub fn resolve_series(
+        &self,
+        names: &Series,
+        emails: &Series,
+        dates: &Series,
+    ) -> PolarsResult<Series> {
+        let names = names.str()?;
+        let emails = emails.str()?;
+        let dates = dates.cast(&DataType::Date)?;
+        let dates = dates.date()?;
+        let resolved: StringChunked = (0..names.len())
+            .map(|i| {
+                let name = names.get(i)?;
+                // `lit("")` 广播前只有一行
+                let email = emails.get(i.min(emails.len() - 1)).unwrap_or("");
+                let date = dates
+                    .get(i)
+                    .map(|days| NaiveDate::default() + chrono::Duration::days(days as i64));
+                self.resolve(name, email, date)
+            })
+            .collect();
+        Ok(resolved.with_name("author").into_series())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(yaml: &str) -> IdentityResolver {
+        let config = config::Config::parse(yaml, std::path::Path::new("x.yml")).unwrap();
+        IdentityResolver::new(&config.authors, &config.bots)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let resolver = resolver(
+            r##"authors:
+  - name: peter
+    alias:
+      - Chen
+      - { email: Peter@Corp.com }
+      - { name: pc, until: 2023-06-30 }
+  - name: bob
+    alias:
+      - { name: pc, since: 2023-07-01 }
+      - { regex: "^[Bb]ob\\b" }
+  - name: vendor
+    alias:
+      - { email_domain: vendor.com }
+bots:
+  - { email: ci@vendor.com }
+  - { regex: "\\[bot\\]$" }
+"##,
+        );
+        let d = |s: &str| s.parse::<NaiveDate>().ok();
+        assert_eq!(resolver.resolve("Chen", "", None), Some("peter"));
+        // 精确匹配，不会匹配到包含alias的名字
+        assert_eq!(resolver.resolve("Chenxi", "", None), None);
+        assert_eq!(
+            resolver.resolve("whoever", "peter@corp.com", None),
+            Some("peter")
+        );
+        assert_eq!(resolver.resolve("pc", "", d("2023-06-30")), Some("peter"));
+        assert_eq!(resolver.resolve("pc", "", d("2023-07-01")), Some("bob"));
+        assert_eq!(resolver.resolve("Bob Smith", "", None), Some("bob"));
+        assert_eq!(resolver.resolve("Bobby", "", None), None);
+        // email优先于域名
+        assert_eq!(resolver.resolve("x", "a@vendor.com", None), Some("vendor"));
+        assert_eq!(resolver.resolve("x", "a@sub.vendor.com", None), None);
+        assert_eq!(resolver.resolve("peter", "", None), Some("peter"));
+        // 机器人优先于作者的域名规则
+        assert_eq!(resolver.resolve("ci", "CI@vendor.com", None), Some(BOTS));
+        assert_eq!(resolver.resolve("dependabot[bot]", "", None), Some(BOTS));
+    }
+}
@@ -0,0 +1,296 @@
+use chrono::{Duration, Utc};
+use git::survey::{self, AuthorCluster, Identity, RepoSurvey};
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// 扫描目录的最大深度
+const SCAN_DEPTH: usize = 3;
+
+/// `git-stat init` 的参数
+pub struct InitOptions {
+    pub urls: Vec<String>,
+    pub dirs: Vec<String>,
+    pub days: i64,
+    pub output: PathBuf,
+    pub yes: bool,
+    pub force: bool,
+}
+
+/// yaml双引号字符串，json字符串的转义规则在yaml中同样适用
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap()
+}
+
+fn quote_list(items: &[String]) -> String {
+    let items: Vec<String> = items.iter().map(|s| quote(s)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// 带注释的配置文件内容
+pub fn render(repos: &[RepoSurvey], authors: &[AuthorCluster], days: i64) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "# generated by `git-stat init` on {}\n",
+        Utc::now().format("%Y-%m-%d")
+    ));
+    out.push_str("# check it with `git-stat config check`, see README for all options\n\n");
+
+    out.push_str(&format!(
+        "# authors seen in the last {} days, names and emails that look like the same person are grouped as aliases\n",
+        days
+    ));
+    out.push_str("authors:\n");
+    if authors.is_empty() {
+        out.push_str("  []\n");
+    }
+    // 多个作者共用的email(没有合并别名时)不能作为alias，否则配置检查会报重复
+    let mut email_owners: HashMap<String, usize> = HashMap::new();
+    for author in authors {
+        let emails: HashSet<String> = author.emails.iter().map(|e| e.to_lowercase()).collect();
+        for email in emails {
+            *email_owners.entry(email).or_default() += 1;
+        }
+    }
+    for author in authors {
+        out.push_str(&format!(
+            "  # {} commits, {}\n",
+            author.commits,
+            author.emails.join(", ")
+        ));
+        out.push_str(&format!("  - name: {}\n", quote(&author.name)));
+        let mut alias: Vec<String> = author.aliases.iter().map(|a| quote(a)).collect();
+        let mut seen = HashSet::new();
+        for email in &author.emails {
+            let key = email.to_lowercase();
+            if email.contains('@') && email_owners[&key] == 1 && seen.insert(key) {
+                alias.push(format!("{{ email: {} }}", quote(email)));
+            }
+        }
+        out.push_str(&format!("    alias: [{}]\n", alias.join(", ")));
+    }
+
+    out.push_str("\nrepos:\n");
+    for repo in repos {
+        if let Some(path) = &repo.path {
+            if path.to_string_lossy() != repo.url {
+                out.push_str(&format!("  # scanned at {}\n", path.display()));
+            }
+        }
+        if !repo.languages.is_empty() {
+            let languages: Vec<String> = repo
+                .languages
+                .iter()
+                .take(5)
+                .map(|(name, code)| format!("{} {}", name, code))
+                .collect();
+            out.push_str(&format!("  # lines of code: {}\n", languages.join(", ")));
+        }
+        out.push_str(&format!("  - url: {}\n", quote(&repo.url)));
+        let branch = repo.default_branch.clone().unwrap_or_default();
+        out.push_str(&format!("    branches: {}\n", quote_list(&[branch])));
+        if repo.pathspec.is_empty() {
+            out.push_str("    pathspec: []   # all files\n");
+        } else {
+            out.push_str(&format!(
+                "    pathspec: {}   # suggested from the dominant languages\n",
+                quote_list(&repo.pathspec)
+            ));
+        }
+    }
+    out
+}
+
+fn confirm(question: &str, default: bool) -> bool {
+    print!("{} [{}] ", question, if default { "Y/n" } else { "y/N" });
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return default;
+    }
+    match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        a => a.starts_with('y'),
+    }
+}
+
+/// 不合并时每个名字单独作为一个作者
+fn split_cluster(cluster: &AuthorCluster, identities: &[Identity]) -> Vec<AuthorCluster> {
+    cluster
+        .aliases
+        .iter()
+        .map(|alias| {
+            let same: Vec<&Identity> = identities.iter().filter(|i| &i.name == alias).collect();
+            AuthorCluster {
+                name: alias.clone(),
+                aliases: vec![alias.clone()],
+                emails: same.iter().map(|i| i.email.clone()).collect(),
+                commits: same.iter().map(|i| i.commits).sum(),
+            }
+        })
+        .collect()
+}
+
+/// 扫描目录和url，询问是否合并别名，写入配置文件
+pub fn run(options: InitOptions) -> Result<(), String> {
+    let interactive = !options.yes && std::io::stdin().is_terminal();
+    if options.output.exists()
+        && !options.force
+        && !(interactive
+            && confirm(
+                &format!("{} exists, overwrite?", options.output.display()),
+                false,
+            ))
+    {
+        return Err(format!(
+            "{} already exists, use --force to overwrite",
+            options.output.display()
+        ));
+    }
+
+    let since = Utc::now() - Duration::days(options.days);
+    let mut surveys = vec![];
+    for url in &options.urls {
+        info!("clone and scan {}", url);
+        match survey::survey_url(url, since) {
+            Ok(s) => surveys.push(s),
+            Err(e) => warn!("scan {} failed: {}", url, e),
+        }
+    }
+    let mut dirs = options.dirs.clone();
+    if dirs.is_empty() && options.urls.is_empty() {
+        dirs.push(".".to_string());
+    }
+    // `./repos` 下是git-stat自己clone的repo
+    let clones = Path::new(git::backend::REPOS_DIR).canonicalize().ok();
+    for dir in &dirs {
+        for path in survey::find_repos(Path::new(dir), SCAN_DEPTH) {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if clones.as_ref().is_some_and(|c| canonical.starts_with(c)) {
+                continue;
+            }
+            info!("scan {}", path.display());
+            match survey::survey_path(&path, since) {
+                Ok(s) => surveys.push(s),
+                Err(e) => warn!("scan {} failed: {}", path.display(), e),
+            }
+        }
+    }
+
+    // 同一个url只保留一次，repo名相同会clone到同一个目录，空repo没有可统计的分支
+    let mut urls = HashSet::new();
+    let mut names = HashSet::new();
+    surveys.retain(|s| {
+        // 多个本地clone指向同一个origin
+        if !urls.insert(s.url.clone()) {
+            return false;
+        }
+        let name = config::Repo::new(&s.url, vec![]).repo_name().to_string();
+        if s.default_branch.is_none() || (s.identities.is_empty() && s.languages.is_empty()) {
+            warn!("skip empty repo: {}", s.url);
+            return false;
+        }
+        if !names.insert(name.clone()) {
+            warn!("skip {}, another repo is already named {}", s.url, name);
+            return false;
+        }
+        true
+    });
+    if surveys.is_empty() {
+        return Err("no git repo found".to_string());
+    }
+
+    let identities: Vec<Identity> = survey::merge_identities(&surveys)
+        .into_iter()
+        .filter(|i| !i.name.is_empty())
+        .collect();
+    let mut authors = vec![];
+    for cluster in survey::cluster_identities(&identities) {
+        if cluster.aliases.len() > 1
+            && interactive
+            && !confirm(
+                &format!(
+                    "treat {} ({}) as one author `{}`?",
+                    cluster.aliases.join(", "),
+                    cluster.emails.join(", "),
+                    cluster.name
+                ),
+                true,
+            )
+        {
+            authors.extend(split_cluster(&cluster, &identities));
+        } else {
+            authors.push(cluster);
+        }
+    }
+
+    let text = render(&surveys, &authors, options.days);
+    if let Err(e) = config::Config::parse(&text, &options.output) {
+        warn!("generated config has problems, please edit it:\n{}", e);
+    }
+    std::fs::write(&options.output, text)
+        .map_err(|e| format!("write {}: {}", options.output.display(), e))?;
+    println!(
+        "wrote {}: {} repos, {} authors",
+        options.output.display(),
+        surveys.len(),
+        authors.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_render() {
+        let repo = RepoSurvey {
+            url: "https://github.com/26huitailang/yogo.git".to_string(),
+            path: Some(PathBuf::from("/src/yogo")),
+            default_branch: Some("main".to_string()),
+            languages: vec![("Go".to_string(), 900)],
+            pathspec: vec!["!vendor".to_string(), "*.go".to_string()],
+            identities: vec![],
+        };
+        let identities = vec![
+            Identity {
+                name: "Peter \"PC\" Chen".to_string(),
+                email: "peter@corp.com".to_string(),
+                commits: 3,
+                last_commit: DateTime::default(),
+            },
+            Identity {
+                name: "peterchen".to_string(),
+                email: "peter@corp.com".to_string(),
+                commits: 1,
+                last_commit: DateTime::default(),
+            },
+        ];
+        let clusters = survey::cluster_identities(&identities);
+        let text = render(&[repo], &clusters, 365);
+        let config = config::Config::parse(&text, Path::new("init.yml")).unwrap();
+        assert_eq!(config.authors.len(), 1);
+        assert_eq!(config.authors[0].name, "Peter \"PC\" Chen");
+        let alias = &config.authors[0].alias;
+        assert_eq!(alias.len(), 3);
+        assert_eq!(
+            alias[2].matcher(),
+            Ok((config::MatchKind::Email, "peter@corp.com"))
+        );
+        assert_eq!(config.repos[0].branches, vec!["main"]);
+        assert_eq!(config.repos[0].pathspec, vec!["!vendor", "*.go"]);
+        assert!(text.contains("# scanned at /src/yogo"));
+
+        let split = split_cluster(&clusters[0], &identities);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[1].name, "peterchen");
+        assert_eq!(split[1].commits, 1);
+        // the shared email is left out so the split authors don't clash
+        let text = render(&[], &split, 365);
+        let config = config::Config::parse(&text, Path::new("init.yml")).unwrap();
+        assert!(config.authors.iter().all(|a| a.alias.len() == 1));
+    }
+}
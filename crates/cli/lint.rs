@@ -0,0 +1,307 @@
+use crate::MyDataFrame;
+use config::MessageLintOptions;
+use polars::prelude::*;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// subject的第一个词，忽略 `feat(scope): ` 前缀
+static FIRST_WORD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:\w+(?:\([^)]*\))?!?:\s*)?([A-Za-z]+)\b").unwrap());
+/// added/adding/adds 这种形式
+static NON_IMPERATIVE_SUFFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[a-z]+[^e\W]ed|[a-z]{3,}ing|[a-z]+[^su\W]s)$").unwrap());
+
+/// 后缀像过去式、进行时、第三人称，实际是祈使句中常见的动词或名词
+///
+/// need/feed/bring/focus 这类词后缀规则本身就不匹配，不需要列出
+const IMPERATIVE_ALLOW: &[&str] = &[
+    "alias", "bias", "canvas", "embed", "redis", "shed", "spring", "string",
+];
+/// 后缀看不出来的不规则过去式
+const NON_IMPERATIVE_DENY: &[&str] = &[
+    "broke", "built", "did", "done", "got", "kept", "made", "ran", "sent", "wrote", "written",
+];
+
+/// subject的第一个词不是祈使形式，先查两个列表，都不在时按后缀判断
+fn is_non_imperative(subject: &str) -> bool {
+    let Some(word) = FIRST_WORD.captures(subject).map(|c| c[1].to_lowercase()) else {
+        return false;
+    };
+    if IMPERATIVE_ALLOW.contains(&word.as_str()) {
+        return false;
+    }
+    NON_IMPERATIVE_DENY.contains(&word.as_str()) || NON_IMPERATIVE_SUFFIX.is_match(&word)
+}
+
+fn subject() -> Expr {
+    col("message").str().split(lit("\n")).list().first()
+}
+
+/// subject开头是任一标记，字母结尾的标记需要是完整的词
+fn wip_pattern(markers: &[String]) -> String {
+    let markers: Vec<String> = markers
+        .iter()
+        .map(|m| {
+            let escaped = regex::escape(m);
+            if m.ends_with(|c: char| c.is_alphanumeric()) {
+                format!(r"{}\b", escaped)
+            } else {
+                escaped
+            }
+        })
+        .collect();
+    format!(r"(?i)^\W*(?:{})", markers.join("|"))
+}
+
+/// 启用的规则，(名字, 违反规则时为true的表达式)
+fn lints(options: &MessageLintOptions) -> Vec<(&'static str, Expr)> {
+    let mut lints = vec![];
+    if let Some(max) = options.max_subject_length {
+        lints.push((
+            "subject_length",
+            subject().str().len_chars().gt(lit(max as u32)),
+        ));
+    }
+    if options.imperative {
+        let check = |s: Series| -> PolarsResult<Option<Series>> {
+            let flags: BooleanChunked = s
+                .str()?
+                .into_iter()
+                .map(|s| s.map(is_non_imperative))
+                .collect();
+            Ok(Some(flags.with_name(s.name()).into_series()))
+        };
+        lints.push((
+            "imperative",
+            subject().map(check, GetOutput::from_type(DataType::Boolean)),
+        ));
+    }
+    if let Some(lines) = options.body_required_lines {
+        let has_body = col("message")
+            .str()
+            .strip_chars(lit(NULL))
+            .str()
+            .contains_literal(lit("\n"));
+        let size = col("insertions") + col("deletions");
+        lints.push((
+            "empty_body",
+            size.gt_eq(lit(lines as i64)).and(has_body.not()),
+        ));
+    }
+    if !options.wip_markers.is_empty() {
+        lints.push((
+            "wip",
+            subject()
+                .str()
+                .contains(lit(wip_pattern(&options.wip_markers)), true),
+        ));
+    }
+    if let Some(pattern) = &options.issue_pattern {
+        lints.push((
+            "issue_key",
+            col("message")
+                .str()
+                .contains(lit(pattern.as_str()), true)
+                .not(),
+        ));
+    }
+    lints
+}
+
+impl MyDataFrame<'_> {
+    /// 每个commit一行，每个规则一个bool列，`violations` 是违反的规则名，逗号分隔
+    fn linted(&self, options: &MessageLintOptions) -> (LazyFrame, Vec<&'static str>) {
+        let lints = lints(options);
+        let names: Vec<&'static str> = lints.iter().map(|(name, _)| *name).collect();
+        let violations = if lints.is_empty() {
+            lit("")
+        } else {
+            concat_str(
+                lints
+                    .iter()
+                    .map(|(name, _)| when(col(name)).then(lit(*name)).otherwise(lit(NULL)))
+                    .collect::<Vec<_>>(),
+                ",",
+                true,
+            )
+        };
+        let q = self
+            .filtered()
+            .unique(
+                Some(vec!["repo".to_string(), "commit_id".to_string()]),
+                UniqueKeepStrategy::First,
+            )
+            .with_columns(
+                lints
+                    .into_iter()
+                    .map(|(name, e)| e.fill_null(lit(false)).alias(name))
+                    .collect::<Vec<_>>(),
+            )
+            .with_column(violations.alias("violations"));
+        (q, names)
+    }
+
+    /// commit message质量：分别按author和repo统计违反各个规则的commit数
+    pub fn message_lint(&self, options: &MessageLintOptions) -> DataFrame {
+        let (q, names) = self.linted(options);
+        let mut aggs = vec![
+            len().alias("commits"),
+            col("violations")
+                .neq(lit(""))
+                .cast(DataType::UInt32)
+                .sum()
+                .alias("violations"),
+        ];
+        for name in &names {
+            aggs.push(col(name).cast(DataType::UInt32).sum());
+        }
+        let by = |level: &str, column: &str| {
+            q.clone()
+                .group_by([col(column).alias("name")])
+                .agg(aggs.clone())
+                .with_column(lit(level).alias("level"))
+        };
+        concat(
+            [by("author", "author"), by("repo", "repo")],
+            UnionArgs::default(),
+        )
+        .unwrap()
+        .with_column(
+            (lit(1.0)
+                - col("violations").cast(DataType::Float64)
+                    / col("commits").cast(DataType::Float64))
+            .alias("clean_share"),
+        )
+        .select([
+            col("level"),
+            col("name"),
+            col("commits"),
+            col("clean_share"),
+            col("*").exclude(["level", "name", "commits", "clean_share"]),
+        ])
+        .sort(["level", "name"], SortMultipleOptions::default())
+        .collect()
+        .unwrap()
+    }
+
+    /// 违反规则的commit，供人工检查
+    pub fn lint_violations(&self, options: &MessageLintOptions) -> DataFrame {
+        let (q, _) = self.linted(options);
+        q.filter(col("violations").neq(lit("")))
+            .select([
+                col("repo"),
+                col("branch"),
+                self.report_time("date"),
+                col("commit_id"),
+                col("author"),
+                col("violations"),
+                subject().alias("subject"),
+            ])
+            .sort(["repo", "date"], SortMultipleOptions::default())
+            .collect()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::filter_options;
+
+    #[test]
+    fn test_non_imperative() {
+        for subject in [
+            "Added user api",
+            "fixes login",
+            "feat(api): adding cache",
+            "Updated deps",
+            "Built release images",
+        ] {
+            assert!(is_non_imperative(subject), "{}", subject);
+        }
+        for subject in [
+            "Add user api",
+            "Fix login",
+            "Process events",
+            "Need more tests",
+            "fix: bring back cache",
+            "Embed version in binary",
+            "String helpers for paths",
+            "Alias old config keys",
+            "refactor(ui): Spring cleanup",
+            "",
+        ] {
+            assert!(!is_non_imperative(subject), "{}", subject);
+        }
+    }
+
+    #[test]
+    fn test_imperative_allow() {
+        // every allowed word is caught by the suffix rule, otherwise listing it does nothing
+        for word in IMPERATIVE_ALLOW {
+            assert!(NON_IMPERATIVE_SUFFIX.is_match(word), "{}", word);
+        }
+        // one word per suffix class that needs the allow list: -ed, -ing, -s
+        for word in ["embed", "string", "alias"] {
+            assert!(NON_IMPERATIVE_SUFFIX.is_match(word), "{}", word);
+            assert!(!is_non_imperative(word), "{}", word);
+        }
+        // not matched by the suffix rule at all
+        for word in ["need", "proceed", "bring", "ping", "focus"] {
+            assert!(!NON_IMPERATIVE_SUFFIX.is_match(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_wip_pattern() {
+        let re = Regex::new(&wip_pattern(&["wip".to_string(), "fixup!".to_string()])).unwrap();
+        assert!(re.is_match("WIP: login"));
+        assert!(re.is_match("[wip] login"));
+        assert!(re.is_match("fixup! Add login"));
+        assert!(!re.is_match("wipe cache"));
+        assert!(!re.is_match("Add wip marker lint"));
+    }
+
+    #[test]
+    fn test_message_lint() {
+        let content = "\
+repo,date,branch,commit_id,author,message,insertions,deletions
+api,2024-02-01 10:00:00,main,c1,peter,\"Add login\n\nAPI-12 details\",500,0
+api,2024-02-02 10:00:00,main,c2,peter,Added login,500,0
+api,2024-02-03 10:00:00,main,c3,bob,WIP logout,1,0
+api,2024-02-04 10:00:00,main,c4,bob,Fix API-13,1,0
+";
+        let df = CsvReadOptions::default()
+            .with_has_header(true)
+            .map_parse_options(|s| s.with_try_parse_dates(true))
+            .into_reader_with_file_handle(std::io::Cursor::new(content))
+            .finish()
+            .unwrap();
+        let opts = filter_options(false);
+        let options = MessageLintOptions {
+            issue_pattern: Some(r"[A-Z]+-\d+".to_string()),
+            ..MessageLintOptions::default()
+        };
+        let my_df = MyDataFrame::new(&df, &opts);
+
+        let violations = my_df.lint_violations(&options);
+        let commits = violations.column("commit_id").unwrap().str().unwrap();
+        assert_eq!(
+            commits.into_iter().collect::<Vec<_>>(),
+            vec![Some("c2"), Some("c3")]
+        );
+        let names = violations.column("violations").unwrap().str().unwrap();
+        assert_eq!(names.get(0), Some("imperative,empty_body,issue_key"));
+        assert_eq!(names.get(1), Some("wip,issue_key"));
+
+        let report = my_df.message_lint(&options);
+        // authors bob, peter + repo api
+        assert_eq!(report.height(), 3);
+        let level = report.column("level").unwrap().str().unwrap();
+        let api = level.into_iter().position(|l| l == Some("repo")).unwrap();
+        let issue = report.column("issue_key").unwrap().u32().unwrap();
+        assert_eq!(issue.get(api), Some(2));
+        let clean = report.column("clean_share").unwrap().f64().unwrap();
+        assert_eq!(clean.get(api), Some(0.5));
+    }
+}
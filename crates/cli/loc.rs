@@ -0,0 +1,223 @@
+use crate::parallel_repos;
+use crate::timezone::ReportTz;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+pub use config::Interval;
+use config::Repo;
+use git::loc::{LocPoint, LocStat};
+use log::error;
+use polars::prelude::*;
+
+/// 一行一个 repo + branch + language
+pub fn loc_df(stats: &[LocStat]) -> DataFrame {
+    let strings = |f: fn(&LocStat) -> &str| stats.iter().map(f).collect::<Vec<_>>();
+    let counts = |f: fn(&LocStat) -> usize| stats.iter().map(|s| f(s) as u64).collect::<Vec<_>>();
+    DataFrame::new(vec![
+        Series::new("repo", strings(|s| &s.repo)),
+        Series::new("branch", strings(|s| &s.branch)),
+        Series::new("commit_id", strings(|s| &s.commit_id)),
+        Series::new("language", strings(|s| &s.language)),
+        Series::new("files", counts(|s| s.counts.files)),
+        Series::new("code", counts(|s| s.counts.code)),
+        Series::new("comment", counts(|s| s.counts.comment)),
+        Series::new("blank", counts(|s| s.counts.blank)),
+    ])
+    .unwrap()
+}
+
+/// 合并所有repo的结果，有repo失败(分支不存在、clone或认证失败)时记录错误后退出
+fn collect_or_exit<T>(results: Vec<Result<Vec<T>, String>>, task: &str) -> Vec<T> {
+    let mut data = vec![];
+    let mut failed = false;
+    for result in results {
+        match result {
+            Ok(items) => data.extend(items),
+            Err(e) => {
+                error!("{} failed: {}", task, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    data
+}
+
+/// 所有repo配置分支head的代码行数
+pub fn get_loc_df(repos: Vec<Repo>) -> DataFrame {
+    let results = parallel_repos(repos, "loc snapshot", |repo| {
+        git::loc::snapshot(repo).map_err(|e| config::redact(&format!("{}: {}", repo.url, e)))
+    });
+    let stats: Vec<LocStat> = collect_or_exit(results, "loc snapshot");
+    loc_df(&stats)
+        .sort(
+            ["repo", "branch", "code"],
+            SortMultipleOptions::default().with_order_descending_multi([false, false, true]),
+        )
+        .unwrap()
+}
+
+fn month_end(date: NaiveDate) -> NaiveDate {
+    let first = date.with_day(1).unwrap();
+    first.checked_add_months(Months::new(1)).unwrap() - Duration::days(1)
+}
+
+/// loc-history按采样间隔计算的时间点
+pub trait Periods {
+    /// 没有指定since时，从end往前推12个周期
+    fn default_start(self, end: NaiveDate) -> NaiveDate;
+    /// [start, end]之间每个周期的最后一天: 月末、周日、或者从start开始每N天
+    fn period_ends(self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate>;
+    /// 采样时间点: 报表时区每个周期最后一天的23:59:59，不晚于until(默认现在)
+    fn boundaries(
+        self,
+        tz: ReportTz,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Vec<DateTime<Utc>>;
+}
+
+impl Periods for Interval {
+    fn default_start(self, end: NaiveDate) -> NaiveDate {
+        let start = match self {
+            Interval::Week => end - Duration::weeks(12),
+            Interval::Month => end.checked_sub_months(Months::new(12)).unwrap(),
+            Interval::Days(n) => end - Duration::days(12 * n as i64),
+        };
+        start + Duration::days(1)
+    }
+
+    fn period_ends(self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        let mut date = match self {
+            Interval::Week => {
+                start + Duration::days((7 - start.weekday().num_days_from_sunday() as i64) % 7)
+            }
+            Interval::Month => month_end(start),
+            Interval::Days(n) => start + Duration::days(n as i64 - 1),
+        };
+        let mut ends = vec![];
+        while date <= end {
+            ends.push(date);
+            date = match self {
+                Interval::Week => date + Duration::weeks(1),
+                Interval::Month => month_end(date + Duration::days(1)),
+                Interval::Days(n) => date + Duration::days(n as i64),
+            };
+        }
+        ends
+    }
+
+    fn boundaries(
+        self,
+        tz: ReportTz,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Vec<DateTime<Utc>> {
+        let until = until.unwrap_or_else(Utc::now);
+        let end = tz.local_time(until).date();
+        let start = match since {
+            Some(since) => tz.local_time(since).date(),
+            None => self.default_start(end),
+        };
+        self.period_ends(start, end)
+            .into_iter()
+            .filter_map(|d| tz.to_utc(d.and_hms_opt(23, 59, 59).unwrap()))
+            .filter(|d| *d <= until)
+            .collect()
+    }
+}
+
+/// 一行一个 repo + branch + 采样日期(报表时区)
+pub fn loc_history_df(points: &[LocPoint], tz: ReportTz) -> DataFrame {
+    let strings = |f: fn(&LocPoint) -> &str| points.iter().map(f).collect::<Vec<_>>();
+    let counts = |f: fn(&LocPoint) -> usize| points.iter().map(|p| f(p) as u64).collect::<Vec<_>>();
+    let dates: Vec<NaiveDate> = points
+        .iter()
+        .map(|p| tz.local_time(p.date).date())
+        .collect();
+    DataFrame::new(vec![
+        Series::new("repo", strings(|p| &p.repo)),
+        Series::new("branch", strings(|p| &p.branch)),
+        Series::new("date", dates),
+        Series::new("commit_id", strings(|p| &p.commit_id)),
+        Series::new("files", counts(|p| p.counts.files)),
+        Series::new("code", counts(|p| p.counts.code)),
+        Series::new("comment", counts(|p| p.counts.comment)),
+        Series::new("blank", counts(|p| p.counts.blank)),
+    ])
+    .unwrap()
+}
+
+/// 所有repo配置分支在每个采样时间点的代码行数
+pub fn get_loc_history_df(
+    repos: Vec<Repo>,
+    boundaries: Vec<DateTime<Utc>>,
+    tz: ReportTz,
+) -> DataFrame {
+    let results = parallel_repos(repos, "loc history", move |repo| {
+        git::loc::history(repo, &boundaries)
+            .map_err(|e| config::redact(&format!("{}: {}", repo.url, e)))
+    });
+    let points: Vec<LocPoint> = collect_or_exit(results, "loc history");
+    loc_history_df(&points, tz)
+        .sort(["repo", "branch", "date"], SortMultipleOptions::default())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!("month".parse::<Interval>(), Ok(Interval::Month));
+        assert_eq!("Week".parse::<Interval>(), Ok(Interval::Week));
+        assert_eq!("14d".parse::<Interval>(), Ok(Interval::Days(14)));
+        assert!("0d".parse::<Interval>().is_err());
+        assert!("quarter".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_period_ends() {
+        let ends = Interval::Month.period_ends(date("2024-01-15"), date("2024-04-30"));
+        assert_eq!(
+            ends,
+            vec![
+                date("2024-01-31"),
+                date("2024-02-29"),
+                date("2024-03-31"),
+                date("2024-04-30")
+            ]
+        );
+        // 2024-03-01 is a Friday
+        let ends = Interval::Week.period_ends(date("2024-03-01"), date("2024-03-17"));
+        assert_eq!(
+            ends,
+            vec![date("2024-03-03"), date("2024-03-10"), date("2024-03-17")]
+        );
+        let ends = Interval::Days(10).period_ends(date("2024-03-01"), date("2024-03-25"));
+        assert_eq!(ends, vec![date("2024-03-10"), date("2024-03-20")]);
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let tz: ReportTz = "+08:00".parse().unwrap();
+        let since = tz.to_utc(date("2024-01-01").and_hms_opt(0, 0, 0).unwrap());
+        let until = tz.to_utc(date("2024-03-15").and_hms_opt(23, 59, 59).unwrap());
+        let boundaries = Interval::Month.boundaries(tz, since, until);
+        let expected: Vec<DateTime<Utc>> = ["2024-01-31T15:59:59Z", "2024-02-29T15:59:59Z"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(boundaries, expected);
+
+        // without since: the last 12 periods before until
+        let boundaries = Interval::Month.boundaries(tz, None, until);
+        assert_eq!(boundaries.len(), 12);
+        assert_eq!(boundaries[11], expected[1]);
+    }
+}
@@ -1,22 +1,47 @@
-use config;
+mod busfactor;
+mod coupling;
+mod heatmap;
+mod hotspot;
+mod identity;
+mod init;
+mod lint;
+mod loc;
+mod merge;
+mod outlier;
+mod profile;
+mod repo_filter;
+mod review;
+mod signature;
+mod team;
+mod timezone;
+
 use config::Repo;
 use git::commit::CommitInfo;
 use git::commit::CommitInfoVec;
-use ui::data::Data;
+use git::merge::{MergeInfo, MergeInfoVec};
+use loc::{Interval, Periods};
+use outlier::OutlierMode;
+use timezone::{DateArg, ReportTz};
+use ui::data::Sheet;
 
-use chrono::{DateTime, Local, NaiveDate};
+use chrono::{DateTime, Utc};
 use clap::builder::PossibleValuesParser;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
 use csv::Writer;
 use env_logger::Env;
 use polars::lazy::dsl::GetOutput;
 use polars::prelude::*;
-use std::collections::{HashMap, HashSet};
 use std::sync::mpsc;
-use std::{error::Error, fs::File, path::Path};
+use std::{
+    error::Error,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 use std::{thread, time};
 
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 /// 写入csv文件
 ///
@@ -44,9 +69,10 @@ pub fn write_csv<P: AsRef<Path>>(
 }
 
 enum OutputType {
-    CSV,
-    TABLE,
-    POLAR,
+    Csv,
+    Table,
+    Polar,
+    Dot,
 }
 
 impl OutputType {
@@ -59,9 +85,47 @@ impl OutputType {
 
     fn from_str(s: &str) -> Option<Self> {
         match s {
-            "csv" => Some(OutputType::CSV),
-            "table" => Some(OutputType::TABLE),
-            "polar" => Some(OutputType::POLAR),
+            "csv" => Some(OutputType::Csv),
+            "table" => Some(OutputType::Table),
+            "polar" => Some(OutputType::Polar),
+            "dot" => Some(OutputType::Dot),
+            _ => None,
+        }
+    }
+}
+
+enum ReportType {
+    Summary,
+    Heatmap,
+    Hotspot,
+    Coupling,
+    Size,
+    Profile,
+    BusFactor,
+    Signatures,
+    Lint,
+    MergeRequests,
+    Review,
+    Loc,
+    LocHistory,
+}
+
+impl ReportType {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "summary" => Some(ReportType::Summary),
+            "heatmap" => Some(ReportType::Heatmap),
+            "hotspot" => Some(ReportType::Hotspot),
+            "coupling" => Some(ReportType::Coupling),
+            "size" => Some(ReportType::Size),
+            "profile" => Some(ReportType::Profile),
+            "bus-factor" => Some(ReportType::BusFactor),
+            "signatures" => Some(ReportType::Signatures),
+            "lint" => Some(ReportType::Lint),
+            "mr" => Some(ReportType::MergeRequests),
+            "review" => Some(ReportType::Review),
+            "loc" => Some(ReportType::Loc),
+            "loc-history" => Some(ReportType::LocHistory),
             _ => None,
         }
     }
@@ -103,38 +167,81 @@ impl Output for CsvOutput {
     fn output(&self) -> Result<(), Box<dyn Error>> {
         let mut file = std::fs::File::create(&self.filename).unwrap();
         let mut m_df = self.df.clone();
-        Ok(CsvWriter::new(&mut file).finish(&mut m_df).unwrap())
+        CsvWriter::new(&mut file).finish(&mut m_df).unwrap();
+        Ok(())
+    }
+}
+
+/// Graphviz DOT，只支持coupling报表
+struct DotOutput {
+    filename: String,
+    df: DataFrame,
+}
+
+impl DotOutput {
+    fn new(filename: String, df: DataFrame) -> Self {
+        DotOutput { filename, df }
+    }
+}
+
+impl Output for DotOutput {
+    fn output(&self) -> Result<(), Box<dyn Error>> {
+        let dot = coupling::to_dot(&self.df)?;
+        std::fs::write(&self.filename, dot)?;
+        info!("dot file written successfully: {}", self.filename);
+        Ok(())
     }
 }
 
 struct TableOutput {
     df: DataFrame,
+    heatmap: bool,
 }
 
 impl TableOutput {
-    fn new(df: DataFrame) -> Self {
-        TableOutput { df }
+    fn new(df: DataFrame, heatmap: bool) -> Self {
+        TableOutput { df, heatmap }
     }
 }
 
-fn convert_df_to_data_vec(df: DataFrame) -> Vec<Data> {
-    let mut d = df
-        .select(["repo", "branch", "author", "insertions", "deletions"])
-        .unwrap();
-
-    let mut j = Vec::<u8>::new();
-    JsonWriter::new(&mut j)
-        .with_json_format(JsonFormat::Json)
-        .finish(&mut d)
-        .unwrap();
-    let rows = serde_json::from_slice::<Vec<Data>>(&j).unwrap();
-    rows
+/// DataFrame所有列转成字符串，浮点数保留两位小数
+fn convert_df_to_sheet(df: &DataFrame) -> Sheet {
+    let header = df
+        .get_column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let columns: Vec<Vec<String>> = df
+        .get_columns()
+        .iter()
+        .map(|s| {
+            if s.dtype().is_float() {
+                let f = s.cast(&DataType::Float64).unwrap();
+                f.f64()
+                    .unwrap()
+                    .into_iter()
+                    .map(|v| v.map(|v| format!("{:.2}", v)).unwrap_or_default())
+                    .collect()
+            } else {
+                let s = s.cast(&DataType::String).unwrap();
+                s.str()
+                    .unwrap()
+                    .into_iter()
+                    .map(|v| v.unwrap_or("").to_string())
+                    .collect()
+            }
+        })
+        .collect();
+    let rows = (0..df.height())
+        .map(|i| columns.iter().map(|c| c[i].clone()).collect())
+        .collect();
+    Sheet::new(header, rows)
 }
 
 impl Output for TableOutput {
     fn output(&self) -> Result<(), Box<dyn Error>> {
-        let data_vec = convert_df_to_data_vec(self.df.clone());
-        ui::tui::run(data_vec)
+        let sheet = convert_df_to_sheet(&self.df).with_heatmap(self.heatmap);
+        ui::tui::run(sheet)
     }
 }
 
@@ -145,12 +252,21 @@ struct Args {
     #[arg(
         short = 'F',
         long = "format",
-        value_parser = PossibleValuesParser::new(["csv", "table", "polar"]),
+        value_parser = PossibleValuesParser::new(["csv", "table", "polar", "dot"]),
         default_value = "polar",
-        help = "output format"
+        help = "output format, dot is only for the coupling report"
     )]
     format: String,
 
+    #[arg(
+        short = 'R',
+        long = "report",
+        value_parser = PossibleValuesParser::new(["summary", "heatmap", "hotspot", "coupling", "size", "profile", "bus-factor", "signatures", "lint", "mr", "review", "loc", "loc-history"]),
+        default_value = "summary",
+        help = "report type, heatmap: commits per author by weekday and hour, hotspot: most changed files, coupling: files/dirs changed together, size: commit size percentiles, profile: author tenure and activity, bus-factor: knowledge concentration per directory, signatures: signed/verified commit share, lint: commit message quality, mr: merge request throughput and cycle time, review: review load per person from mr_export, loc: lines of code at branch heads, loc-history: lines of code at the end of each --interval"
+    )]
+    report: String,
+
     #[arg(
         long = "detail",
         help = "keep detail csv file or not, e.g. --detail output.csv"
@@ -164,48 +280,189 @@ struct Args {
     source: Option<String>,
 
     /// since date
-    #[arg(long = "since", value_parser = parse_since, help = "since date, 2024-01-01")]
-    since: Option<DateTime<Local>>,
+    #[arg(long = "since", value_parser = parse_since, help = "since date, 2024-01-01 or 2024-01-01T09:00:00+08:00")]
+    since: Option<DateArg>,
 
     /// until date
-    #[arg(long = "until", value_parser = parse_until, help = "since date, 2024-03-31")]
-    until: Option<DateTime<Local>>,
+    #[arg(long = "until", value_parser = parse_until, help = "until date, 2024-03-31 or 2024-03-31T18:00:00+08:00")]
+    until: Option<DateArg>,
+
+    #[arg(
+        long = "tz",
+        help = "timezone for reports and date filters: local, utc, +08:00 (default: config timezone or local)"
+    )]
+    tz: Option<ReportTz>,
+
+    #[arg(
+        long = "interval",
+        help = "sampling interval of the loc-history report: month, week, 14d (default: config loc_interval or month)"
+    )]
+    interval: Option<Interval>,
 
     #[arg(long = "force-update", action=clap::ArgAction::SetTrue, help="pull from remote repo")]
     update: bool,
+
+    #[arg(long = "dedup", action=clap::ArgAction::SetTrue, help="count each change (same patch-id) only once, attributed to its earliest commit")]
+    dedup: bool,
+
+    #[arg(
+        long = "outliers",
+        value_parser = PossibleValuesParser::new(["keep", "cap", "exclude"]),
+        default_value = "keep",
+        help = "how to count outlier (oversized) commits: keep, cap to the threshold, exclude"
+    )]
+    outliers: String,
+
+    #[arg(
+        long = "config",
+        help = "config file (default: .git-stat.yml in the current directory, then in the git root, then $XDG_CONFIG_HOME/git-stat/config.yml)"
+    )]
+    config: Option<String>,
+
+    #[arg(
+        long = "profile",
+        help = "named profile from the config `profiles`, layered on top of the shared settings"
+    )]
+    profile: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// list outlier (oversized) commits for review
+    Outliers,
+    /// list commits whose message violates the message_lint rules
+    Lint,
+    /// list merge/pull requests reconstructed from merge commits
+    Merges,
+    /// scan git repos (or clone URLS) and write a commented config file
+    Init {
+        /// repo urls to clone into ./repos and scan
+        urls: Vec<String>,
+        #[arg(
+            long = "dir",
+            help = "directory to scan for git repos, repeatable (default: current directory when no url is given)"
+        )]
+        dirs: Vec<String>,
+        #[arg(
+            long = "days",
+            default_value_t = 365,
+            help = "collect authors from the last DAYS days"
+        )]
+        days: i64,
+        #[arg(
+            short = 'o',
+            long = "output",
+            default_value = config::CONFIG_FILE,
+            help = "config file to write"
+        )]
+        output: String,
+        #[arg(short = 'y', long = "yes", action=clap::ArgAction::SetTrue, help="accept suggested alias groups without asking")]
+        yes: bool,
+        #[arg(long = "force", action=clap::ArgAction::SetTrue, help="overwrite an existing config file")]
+        force: bool,
+    },
+    /// inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
 }
 
-fn parse_since(s: &str) -> Result<DateTime<Local>, Box<std::io::Error>> {
-    let since = parse_date(s, [0, 0, 0]).unwrap();
-    info!("since: {}", since);
-    Ok(since)
+#[derive(Subcommand, Debug, Clone)]
+enum ConfigCommand {
+    /// validate the config file (and every profile unless --profile is given), print every problem with its line and column
+    Check,
 }
-fn parse_until(s: &str) -> Result<DateTime<Local>, Box<std::io::Error>> {
-    let until = parse_date(s, [23, 59, 59]).unwrap();
-    info!("until: {}", until);
-    Ok(until)
+
+/// `--config` 指定的文件，否则依次查找当前目录、git根目录、`$XDG_CONFIG_HOME/git-stat/config.yml`
+fn config_path(args: &Args) -> PathBuf {
+    if let Some(path) = &args.config {
+        return PathBuf::from(path);
+    }
+    config::discover().unwrap_or_else(|| {
+        error!(
+            "no config file found: create {} in the current directory or git root, {}, or pass --config",
+            config::CONFIG_FILE,
+            "$XDG_CONFIG_HOME/git-stat/config.yml"
+        );
+        std::process::exit(1);
+    })
 }
 
-fn parse_date(s: &str, hms_opt: [u32; 3]) -> Result<DateTime<Local>, Box<std::io::Error>> {
-    let date = match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
-        Ok(d) => d,
-        Err(e) => {
-            error!("parse date err: {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid date format",
-            )));
+/// 配置有问题时列出所有错误后退出，不panic
+fn load_config(path: &Path, profile: Option<&str>) -> config::Config {
+    info!("config file: {}, profile: {:?}", path.display(), profile);
+    let conf = config::Config::load(path, profile).unwrap_or_else(|e| {
+        error!("invalid config:\n{}", e);
+        std::process::exit(1);
+    });
+    if conf.repos.is_empty() {
+        let profiles: Vec<&str> = conf.profiles.keys().map(String::as_str).collect();
+        if profile.is_none() && !profiles.is_empty() {
+            error!(
+                "no repos in {}, select a profile with --profile: {}",
+                path.display(),
+                profiles.join(", ")
+            );
+        } else {
+            error!("no repos in {}", path.display());
         }
-    };
-    let d = date.and_hms_opt(hms_opt[0], hms_opt[1], hms_opt[2]);
-    Ok(d.unwrap().and_local_timezone(Local).unwrap())
+        std::process::exit(1);
+    }
+    conf
+}
+
+/// `config check`：没有 `--profile` 时检查基础配置和每个profile
+fn config_check(path: &Path, profile: Option<&str>) -> Result<Vec<String>, String> {
+    let base = config::Config::load(path, profile).map_err(|e| e.to_string())?;
+    let mut names = vec![profile.map(String::from)];
+    if profile.is_none() {
+        names.extend(base.profiles.keys().cloned().map(Some));
+    }
+    let mut lines = vec![];
+    let mut problems = vec![];
+    for name in names {
+        let label = match &name {
+            Some(name) => format!("{} (profile {})", path.display(), name),
+            None => path.display().to_string(),
+        };
+        let conf = match config::Config::load(path, name.as_deref()) {
+            Ok(conf) => conf,
+            Err(e) => {
+                problems.push(e.to_string());
+                continue;
+            }
+        };
+        lines.push(format!(
+            "{}: ok, {} repos, {} authors",
+            label,
+            conf.repos.len(),
+            conf.authors.len()
+        ));
+    }
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+    Ok(lines)
+}
+
+fn parse_since(s: &str) -> Result<DateArg, String> {
+    DateArg::parse(s, [0, 0, 0]).inspect_err(|e| error!("parse since err: {}", e))
+}
+fn parse_until(s: &str) -> Result<DateArg, String> {
+    DateArg::parse(s, [23, 59, 59]).inspect_err(|e| error!("parse until err: {}", e))
 }
 
-fn get_output(output_type: OutputType, df: DataFrame) -> Box<dyn Output> {
+fn get_output(output_type: OutputType, report_type: &ReportType, df: DataFrame) -> Box<dyn Output> {
+    let heatmap = matches!(report_type, ReportType::Heatmap);
     match output_type {
-        OutputType::TABLE => Box::new(TableOutput::new(df)),
-        OutputType::CSV => Box::new(CsvOutput::new(String::from("report.csv"), df)),
-        OutputType::POLAR => Box::new(PolarOutput::new(df)),
+        OutputType::Table => Box::new(TableOutput::new(df, heatmap)),
+        OutputType::Csv => Box::new(CsvOutput::new(String::from("report.csv"), df)),
+        OutputType::Polar => Box::new(PolarOutput::new(df)),
+        OutputType::Dot => Box::new(DotOutput::new(String::from("report.dot"), df)),
     }
 }
 
@@ -220,156 +477,981 @@ fn load_df_from_csv(filename: String) -> DataFrame {
 
 #[derive(Debug)]
 pub struct FilterOptions {
-    pub since: Option<DateTime<Local>>,
-    pub until: Option<DateTime<Local>>,
-    pub authors: HashMap<String, String>,
+    /// detail中的date是UTC，since/until也换算成UTC比较
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub authors: identity::IdentityResolver,
+    /// 配置了团队时summary按团队分组并加小计
+    pub teams: team::TeamResolver,
+    /// repo各自的日期范围和排除规则
+    pub repo_filters: Vec<repo_filter::RepoFilter>,
+    /// count cherry-picked/duplicated commits (same patch_id) only once
+    pub dedup: bool,
+    pub business_hours: config::BusinessHours,
+    pub outliers: OutlierMode,
+    /// 报表中输出的时间和按月分组使用的时区
+    pub tz: ReportTz,
 }
 
 pub struct MyDataFrame<'a> {
     df: &'a DataFrame,
+    /// 文件级别的detail，文件相关的报表需要
+    files: Option<&'a DataFrame>,
+    /// merge commit还原的合并(MR/PR)
+    merges: Option<&'a DataFrame>,
     filter_options: &'a FilterOptions,
 }
 
 impl<'a> MyDataFrame<'a> {
     pub fn new(df: &'a DataFrame, filter_options: &'a FilterOptions) -> Self {
-        MyDataFrame { df, filter_options }
+        MyDataFrame {
+            df,
+            files: None,
+            merges: None,
+            filter_options,
+        }
     }
-    pub fn summary(&self) -> DataFrame {
+
+    pub fn with_files(mut self, files: &'a DataFrame) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    pub fn with_merges(mut self, merges: &'a DataFrame) -> Self {
+        self.merges = Some(merges);
+        self
+    }
+    /// 相同patch_id的commit视为同一个变更，只保留最早的一条
+    fn dedup_patches(&self, q: LazyFrame) -> LazyFrame {
+        if self.df.get_column_index("patch_id").is_none() {
+            warn!("no patch_id column in detail data, skip dedup");
+            return q;
+        }
+        // keep commits without patch_id, they can't be matched to anything
+        let no_patch = q.clone().filter(col("patch_id").is_null());
+        let deduped = q
+            .filter(col("patch_id").is_not_null())
+            .sort(
+                ["date"],
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
+            .unique_stable(
+                Some(vec!["patch_id".to_string()]),
+                UniqueKeepStrategy::First,
+            );
+        concat([deduped, no_patch], UnionArgs::default()).unwrap()
+    }
+
+    /// patch_id分组，列出被重复计算的变更及其所有出现的位置
+    pub fn patch_groups(&self) -> DataFrame {
+        self.df
+            .clone()
+            .lazy()
+            .filter(col("patch_id").is_not_null())
+            .sort(
+                ["date"],
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
+            .group_by_stable([col("patch_id")])
+            .agg([
+                col("commit_id").count().alias("count"),
+                col("date").first().alias("first_date"),
+                col("repo").first().alias("first_repo"),
+                col("branch").first().alias("first_branch"),
+                col("commit_id").first().alias("first_commit_id"),
+            ])
+            .filter(col("count").gt(lit(1)))
+            .collect()
+            .unwrap()
+    }
+
+    pub fn has_column(&self, name: &str) -> bool {
+        self.df.get_column_index(name).is_some()
+    }
+
+    /// UTC的时间列`column`换算成报表时区，列名不变
+    pub fn report_time(&self, column: &str) -> Expr {
+        self.filter_options.tz.local_expr(col(column)).alias(column)
+    }
+
+    /// `column` 中的作者alias按 `date` 列的日期替换成配置的作者名，只按名字匹配，没有匹配的保持原样
+    pub fn map_aliases(&self, q: LazyFrame, column: &str, date: &str) -> LazyFrame {
+        let resolver = self.filter_options.authors.clone();
+        let map = move |s: &mut [Series]| -> PolarsResult<Option<Series>> {
+            let resolved = resolver.resolve_series(&s[0], &s[1], &s[2])?;
+            let names: StringChunked = resolved
+                .str()?
+                .into_iter()
+                .zip(s[0].str()?)
+                .map(|(author, name)| author.or(name))
+                .collect();
+            Ok(Some(names.with_name(s[0].name()).into_series()))
+        };
+        q.with_column(col(column).map_many(
+            map,
+            &[lit(""), col(date)],
+            GetOutput::from_type(DataType::String),
+        ))
+    }
+
+    /// 作者身份解析、作者和时间过滤、去重，各种报表共用
+    ///
+    /// 去重在过滤之后，相同的变更归属于实际统计的commit中最早的一个
+    pub fn filtered(&self) -> LazyFrame {
         let mut q = self.df.clone().lazy();
-        let mut allowed_authors: HashSet<String> = HashSet::new();
-        for (alias, author) in &self.filter_options.authors {
-            q = q.with_column(col("author").str().replace(
-                lit(alias.as_str()),
-                lit(author.as_str()),
-                false,
-            ));
-            allowed_authors.insert(author.into());
+
+        // 旧的detail没有email列，只能按名字匹配
+        let with_email = self.has_column("email");
+        let email = if with_email { col("email") } else { lit("") };
+        let resolver = self.filter_options.authors.clone();
+        let resolve = move |s: &mut [Series]| -> PolarsResult<Option<Series>> {
+            let resolved = resolver.resolve_series(&s[0], &s[1], &s[2])?;
+            Ok(Some(resolved))
+        };
+        q = q
+            .with_column(col("author").alias("git_author"))
+            .with_column(
+                col("author")
+                    .map_many(
+                        resolve,
+                        &[email, col("date")],
+                        GetOutput::from_type(DataType::String),
+                    )
+                    .alias("author"),
+            )
+            .filter(col("author").is_not_null());
+
+        let repo_filters = &self.filter_options.repo_filters;
+        if !with_email && repo_filters.iter().any(|f| f.has_email_patterns()) {
+            warn!("no email column in detail data, exclude_emails is ignored");
         }
+        q = q
+            .filter(repo_filter::excluded(repo_filters, with_email).not())
+            .drop(["git_author"]);
 
         let mut filter_expr = lit(true);
 
+        if self.df.get_column_index("tz_offset").is_none() {
+            warn!("no tz_offset column in detail data, dates are compared as UTC");
+        }
         if let Some(since) = self.filter_options.since {
-            let since_expr = lit(since.naive_local());
+            let since_expr = lit(since.naive_utc());
             filter_expr = filter_expr.and(col("date").gt_eq(since_expr));
         };
         if let Some(until) = self.filter_options.until {
-            let until_expr = lit(until.naive_local());
+            let until_expr = lit(until.naive_utc());
             filter_expr = filter_expr.and(col("date").lt_eq(until_expr));
         };
 
-        let is_valid = move |s: Series| -> Result<Option<Series>, _> {
-            let ss: Vec<_> = s
-                .str()
-                .unwrap()
-                .into_iter()
-                .map(|s| allowed_authors.contains(s.unwrap_or("")))
-                .collect();
-            let s = Series::new("is_valid", ss);
-            Ok(Some(s))
+        q = q.filter(filter_expr);
+        if self.filter_options.dedup {
+            q = self.dedup_patches(q);
+        }
+        if self.has_column("outlier") {
+            let columns = self.df.get_column_names();
+            q = outlier::apply_outlier_mode(q, self.filter_options.outliers, &columns);
+        }
+        q
+    }
+
+    /// 文件detail只保留过滤后commit中的文件，并带上commit的date和(alias后的)author，没有文件detail时返回错误
+    pub fn filtered_files(&self) -> Result<LazyFrame, String> {
+        let Some(files) = self.files else {
+            return Err(
+                "no files detail, parse repos again or provide <source>_files.csv".to_string(),
+            );
         };
-        let o = GetOutput::from_type(DataType::Boolean);
-        q = q
-            .with_column(
-                col("author")
-                    .map(is_valid, o)
-                    .alias("is_valid")
-                    .cast(DataType::Boolean),
+        let files = files.clone().lazy();
+        let commits = self.filtered().select([
+            col("repo"),
+            col("branch"),
+            col("commit_id"),
+            col("date"),
+            col("author"),
+        ]);
+        let keys = [col("repo"), col("branch"), col("commit_id")];
+        Ok(files.join(commits, keys.clone(), keys, JoinArgs::new(JoinType::Inner)))
+    }
+
+    pub fn summary(&self) -> DataFrame {
+        let business_hours = &self.filter_options.business_hours;
+        let mut aggs = vec![
+            col("insertions").sum(),
+            col("deletions").sum(),
+            col("outside_hours")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("outside_hours_share"),
+        ];
+        // 老的detail数据没有分类列
+        let with_categories = self.has_column("test_insertions");
+        if with_categories {
+            for category in config::FileCategory::ALL {
+                for kind in ["insertions", "deletions"] {
+                    aggs.push(col(&format!("{}_{}", category.as_str(), kind)).sum());
+                }
+            }
+        }
+        let commits = self.filtered().with_column(
+            heatmap::outside_hours(self.author_local_date(), business_hours).alias("outside_hours"),
+        );
+        let teams = &self.filter_options.teams;
+        let mut q = if teams.is_empty() {
+            commits.group_by(["repo", "branch", "author"]).agg(aggs)
+        } else {
+            self.team_rollup(commits, aggs)
+        };
+        if with_categories {
+            // test改动行数 / production改动行数，没有production改动时为空
+            let test = col("test_insertions") + col("test_deletions");
+            let production = col("production_insertions") + col("production_deletions");
+            q = q.with_column(
+                when(production.clone().gt(lit(0)))
+                    .then(test.cast(DataType::Float64) / production.cast(DataType::Float64))
+                    .otherwise(lit(NULL))
+                    .alias("test_ratio"),
+            );
+        }
+        let q = if teams.is_empty() {
+            q.sort(["repo", "branch"], SortMultipleOptions::default())
+        } else {
+            // 小计行在所属团队的成员之前，不属于任何团队的作者在最后
+            q.sort(
+                ["org", "team", "repo", "branch", "author"],
+                SortMultipleOptions::default()
+                    .with_nulls_last_multi([true, true, false, false, false]),
             )
-            .filter(col("is_valid"));
-
-        q.filter(filter_expr)
-            .select(vec![
-                col("repo"),
-                col("branch"),
-                col("author"),
-                col("insertions"),
-                col("deletions"),
-            ])
-            .group_by(["repo", "branch", "author"])
-            .agg([col("*").sum()])
-            .sort(["repo", "branch"], SortMultipleOptions::default())
-            .collect()
-            .unwrap()
+        };
+        q.collect().unwrap()
+    }
+
+    /// 按commit日期所属的团队分组，每一级团队(包括顶层的org)加一行小计，小计行的repo/branch/author为空
+    fn team_rollup(&self, commits: LazyFrame, aggs: Vec<Expr>) -> LazyFrame {
+        let teams = self.filter_options.teams.clone();
+        let resolve = move |s: &mut [Series]| -> PolarsResult<Option<Series>> {
+            Ok(Some(teams.resolve_series(&s[0], &s[1])?))
+        };
+        let commits = commits.with_column(
+            col("author")
+                .map_many(
+                    resolve,
+                    &[col("date")],
+                    GetOutput::from_type(DataType::String),
+                )
+                .alias("team"),
+        );
+        let columns = [
+            col("org"),
+            col("team"),
+            col("repo"),
+            col("branch"),
+            col("author"),
+        ];
+        let members = commits
+            .clone()
+            .group_by(["team", "repo", "branch", "author"])
+            .agg(aggs.clone())
+            .with_column(team::org(col("team")).alias("org"));
+        let mut frames = vec![members];
+        for path in self.filter_options.teams.paths() {
+            let in_team = col("team")
+                .eq(lit(path.as_str()))
+                .or(col("team").str().starts_with(lit(format!("{}/", path))));
+            let subtotal = commits
+                .clone()
+                .filter(in_team)
+                .with_column(lit(path.as_str()).alias("team"))
+                .group_by(["team"])
+                .agg(aggs.clone())
+                .with_columns([
+                    team::org(col("team")).alias("org"),
+                    lit(NULL).cast(DataType::String).alias("repo"),
+                    lit(NULL).cast(DataType::String).alias("branch"),
+                    lit(NULL).cast(DataType::String).alias("author"),
+                ]);
+            frames.push(subtotal);
+        }
+        let frames: Vec<LazyFrame> = frames
+            .into_iter()
+            .map(|q| {
+                q.select(
+                    [
+                        columns.to_vec(),
+                        vec![all().exclude(["org", "team", "repo", "branch", "author"])],
+                    ]
+                    .concat(),
+                )
+            })
+            .collect();
+        concat(frames, UnionArgs::default()).unwrap()
     }
 }
 
-pub fn get_df(source: Option<String>, repos: Vec<Repo>, update: bool) -> DataFrame {
+/// 文件级别、合并等detail的文件名，和commit detail放在一起: detail.csv => detail_files.csv
+fn detail_path(detail: &str, kind: &str) -> String {
+    let path = Path::new(detail);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = format!("{}_{}.csv", stem, kind);
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+fn read_cursor(cursor: std::io::Cursor<Vec<u8>>) -> DataFrame {
+    CsvReadOptions::default()
+        .with_has_header(true)
+        .map_parse_options(|s| s.with_try_parse_dates(true))
+        .into_reader_with_file_handle(cursor)
+        .finish()
+        .unwrap()
+}
+
+/// 每个repo一个线程执行`f`，收集所有结果
+pub fn parallel_repos<T, F>(repos: Vec<Repo>, task: &'static str, f: F) -> Vec<T>
+where
+    T: Send + 'static,
+    F: Fn(&Repo) -> T + Send + Clone + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut handlers = vec![];
+
+    for repo in repos {
+        let t_sender = tx.clone();
+        let f = f.clone();
+        let t = thread::spawn(move || {
+            let repo_name = repo.repo_name();
+            info!("{} start: {}", task, repo_name);
+            let start = time::Instant::now();
+            let data = f(&repo);
+            t_sender.send(data).unwrap();
+            let duration = time::Instant::now().duration_since(start);
+            info!(
+                "{} done: {}, cost {}ms",
+                task,
+                repo_name,
+                duration.as_millis()
+            );
+        });
+        handlers.push(t);
+    }
+    for h in handlers {
+        h.join().unwrap();
+    }
+    drop(tx);
+    info!("rx start to collect data");
+    let mut results = vec![];
+    while let Ok(received) = rx.recv() {
+        results.push(received);
+    }
+    info!("rx collect data done");
+    results
+}
+
+/// commit detail，以及文件级别和合并的detail
+pub struct Detail {
+    pub commits: DataFrame,
+    /// `--source`没有对应的文件时为None
+    pub files: Option<DataFrame>,
+    pub merges: Option<DataFrame>,
+}
+
+/// `--source`对应的附属detail，不存在时为None
+fn load_optional_detail(source: &str, kind: &str) -> Option<DataFrame> {
+    let path = detail_path(source, kind);
+    if Path::new(&path).exists() {
+        Some(load_df_from_csv(path))
+    } else {
+        warn!("no {} detail found: {}", kind, path);
+        None
+    }
+}
+
+pub fn get_df(
+    source: Option<String>,
+    repos: Vec<Repo>,
+    signatures: &config::SignatureOptions,
+    update: bool,
+) -> Detail {
     let mut repo_data: Vec<CommitInfo> = vec![];
-    let df = match source {
-        Some(source) => load_df_from_csv(source),
+    let mut merge_data: Vec<MergeInfo> = vec![];
+    match source {
+        Some(source) => Detail {
+            files: load_optional_detail(&source, "files"),
+            merges: load_optional_detail(&source, "merges"),
+            commits: load_df_from_csv(source),
+        },
         None => {
-            let (tx, rx) = mpsc::channel();
-            let mut handlers = vec![];
-
-            for repo in repos {
-                let t_sender = tx.clone();
-                let t = thread::spawn(move || {
-                    let repo_name = repo.repo_name();
-                    info!("repo parse start: {}", repo_name);
-                    let start = time::Instant::now();
-                    let data = git::commit::repo_parse(&repo, update).unwrap();
-                    t_sender.send(data).unwrap();
-                    let duration = time::Instant::now().duration_since(start);
-                    info!(
-                        "repo parse done: {}, cost {}ms",
-                        repo_name,
-                        duration.as_millis()
-                    );
-                });
-                handlers.push(t);
+            let signatures = signatures.clone();
+            let results = parallel_repos(repos, "repo parse", move |repo| {
+                git::commit::repo_parse(repo, &signatures, update)
+                    .map_err(|e| config::redact(&format!("{}: {}", repo.url, e)))
+            });
+            let mut failed = false;
+            for result in results {
+                match result {
+                    Ok(data) => {
+                        repo_data.extend(data.commits);
+                        merge_data.extend(data.merges);
+                    }
+                    Err(e) => {
+                        error!("parse repo failed: {}", e);
+                        failed = true;
+                    }
+                }
             }
-            for h in handlers {
-                h.join().unwrap();
+            if failed {
+                std::process::exit(1);
             }
-            drop(tx);
-            info!("rx start to collect data");
-            while let Ok(received) = rx.recv() {
-                debug!("rx received data len {}", received.len());
-                repo_data.extend(received);
+
+            let commits = CommitInfoVec::new(repo_data);
+            let merges = MergeInfoVec::new(merge_data);
+            Detail {
+                commits: read_cursor(commits.file_cursor().unwrap()),
+                files: Some(read_cursor(commits.files_cursor().unwrap())),
+                merges: Some(read_cursor(merges.file_cursor().unwrap())),
             }
-            info!("rx collect data done");
-
-            let file = CommitInfoVec::new(repo_data).file_cursor().unwrap();
-            CsvReadOptions::default()
-                .with_has_header(true)
-                .map_parse_options(|s| s.with_try_parse_dates(true))
-                .into_reader_with_file_handle(file)
-                .finish()
-                .unwrap()
         }
-    };
-    df
+    }
 }
 fn main() {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    // 日志中的密码、token替换为 ***，包括Debug输出和git命令的参数、环境变量
+    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+        .format(|buf, record| {
+            let style = buf.default_level_style(record.level());
+            writeln!(
+                buf,
+                "[{} {style}{:<5}{style:#} {}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                config::redact(&record.args().to_string())
+            )
+        })
+        .init();
 
     let args = Args::parse();
-    let conf = config::Config::new(".git-stat.yml");
+    if let Some(Command::Init {
+        urls,
+        dirs,
+        days,
+        output,
+        yes,
+        force,
+    }) = &args.command
+    {
+        let options = init::InitOptions {
+            urls: urls.clone(),
+            dirs: dirs.clone(),
+            days: *days,
+            output: PathBuf::from(output),
+            yes: *yes,
+            force: *force,
+        };
+        if let Err(e) = init::run(options) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Config {
+        action: ConfigCommand::Check,
+    }) = &args.command
+    {
+        match config_check(&config_path(&args), args.profile.as_deref()) {
+            Ok(lines) => lines.iter().for_each(|l| println!("{}", l)),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.format == "dot" && (args.command.is_some() || args.report != "coupling") {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--format dot is only supported by the coupling report",
+            )
+            .exit();
+    }
+    let conf = load_config(&config_path(&args), args.profile.as_deref());
+
+    let report_type = ReportType::from_str(args.report.as_str()).unwrap();
+    let out_type = OutputType::from_str(args.format.as_str()).unwrap();
+    let tz = match args.tz {
+        Some(tz) => tz,
+        None => conf
+            .timezone
+            .as_deref()
+            // 加载配置时已经检查过
+            .map(|s| s.parse::<ReportTz>().unwrap())
+            .unwrap_or_default(),
+    };
+    // 本地时区夏令时跳过的时间不存在
+    let resolve = |arg: &str, date: Option<DateArg>| {
+        date.map(|d| {
+            d.resolve(&tz).unwrap_or_else(|| {
+                Args::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("--{} {} does not exist in timezone {}", arg, d, tz),
+                    )
+                    .exit()
+            })
+        })
+    };
+    let since = resolve("since", args.since);
+    let until = resolve("until", args.until);
+    info!("timezone: {}, since: {:?}, until: {:?}", tz, since, until);
+
+    // 代码行数直接读repo，不需要commit detail
+    if args.command.is_none() && matches!(report_type, ReportType::Loc | ReportType::LocHistory) {
+        let report = match report_type {
+            ReportType::LocHistory => {
+                let interval = args.interval.unwrap_or_else(|| {
+                    conf.loc_interval
+                        .as_deref()
+                        .map(|s| s.parse::<Interval>().unwrap())
+                        .unwrap_or(Interval::Month)
+                });
+                let boundaries = interval.boundaries(tz, since, until);
+                info!(
+                    "loc history interval: {:?}, {} points",
+                    interval,
+                    boundaries.len()
+                );
+                loc::get_loc_history_df(conf.repos, boundaries, tz)
+            }
+            _ => loc::get_loc_df(conf.repos),
+        };
+        get_output(out_type, &report_type, report)
+            .output()
+            .expect("output failed");
+        return;
+    }
 
-    let df = get_df(args.source, conf.repos, args.update);
+    // 只有review报表需要MR元数据
+    let mr_metadata = if args.command.is_none() && matches!(report_type, ReportType::Review) {
+        match review::load_mr_exports(&conf.repos) {
+            Ok(Some(metadata)) => Some(metadata),
+            Ok(None) => {
+                error!("review report needs `mr_export` configured for at least one repo");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let repo_filters = repo_filter::RepoFilter::from_repos(&conf.repos);
+    let detail = get_df(args.source, conf.repos, &conf.signatures, args.update);
+    let df = match outlier::flag_outliers(detail.commits, &conf.outliers) {
+        Ok(df) => df,
+        Err(e) => {
+            error!("flag outliers failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let files_df = detail.files;
+    let merges_df = detail.merges;
 
     if !args.no_detail {
         let detail_file = args.detail.clone().unwrap_or("detail.csv".to_string());
         info!("detail csv file: {}", detail_file);
+        if let Some(files_df) = &files_df {
+            CsvOutput::new(detail_path(&detail_file, "files"), files_df.clone())
+                .output()
+                .expect("files detail csv output failed");
+        }
+        if let Some(merges_df) = &merges_df {
+            CsvOutput::new(detail_path(&detail_file, "merges"), merges_df.clone())
+                .output()
+                .expect("merges detail csv output failed");
+        }
         CsvOutput::new(detail_file, df.clone())
             .output()
             .expect("detail csv output failed");
     }
     // summary by polars
     let filter_options = &mut FilterOptions {
-        since: args.since,
-        until: args.until,
-        authors: HashMap::new(),
+        since,
+        until,
+        authors: identity::IdentityResolver::new(&conf.authors, &conf.bots),
+        teams: team::TeamResolver::new(&conf.teams),
+        repo_filters,
+        dedup: args.dedup,
+        business_hours: conf.business_hours.clone(),
+        outliers: OutlierMode::from_str(args.outliers.as_str()).unwrap(),
+        tz,
     };
-    for author in conf.authors {
-        for alias in author.alias {
-            filter_options.authors.insert(alias, author.name.clone());
+    debug!("filter options: {:?}", filter_options);
+    let mut my_df = MyDataFrame::new(&df, filter_options);
+    if let Some(files_df) = &files_df {
+        my_df = my_df.with_files(files_df);
+    }
+    if let Some(merges_df) = &merges_df {
+        my_df = my_df.with_merges(merges_df);
+    }
+    if args.dedup && df.get_column_index("patch_id").is_some() {
+        let groups = my_df.patch_groups();
+        info!("duplicated changes (same patch-id): {}", groups.height());
+        debug!("{}", groups);
+    }
+    // 文件级别和合并的报表缺少detail时返回错误
+    let report = match (&args.command, &report_type) {
+        (Some(Command::Outliers), _) => Ok(my_df.outliers()),
+        (Some(Command::Lint), _) => Ok(my_df.lint_violations(&conf.message_lint)),
+        (Some(Command::Merges), _) => my_df.merge_list(),
+        (Some(Command::Config { .. } | Command::Init { .. }), _) => {
+            unreachable!("config and init commands return early")
         }
+        (None, _) => match report_type {
+            ReportType::Summary => Ok(my_df.summary()),
+            ReportType::Heatmap => Ok(my_df.heatmap()),
+            ReportType::Hotspot => my_df.hotspot(&conf.hotspot),
+            ReportType::Coupling => my_df.coupling(&conf.coupling),
+            ReportType::Size => Ok(my_df.size_distribution()),
+            ReportType::Profile => Ok(my_df.profile()),
+            ReportType::BusFactor => my_df.bus_factor(&conf.bus_factor),
+            ReportType::Signatures => Ok(my_df.signatures()),
+            ReportType::Lint => Ok(my_df.message_lint(&conf.message_lint)),
+            ReportType::MergeRequests => my_df.merge_requests(),
+            ReportType::Review => Ok(my_df.review_load(mr_metadata.as_ref().unwrap())),
+            ReportType::Loc | ReportType::LocHistory => {
+                unreachable!("loc reports are handled before parsing commits")
+            }
+        },
+    };
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    get_output(out_type, &report_type, report)
+        .output()
+        .expect("output failed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn detail_df() -> DataFrame {
+        let content = "\
+repo,date,tz_offset,branch,commit_id,author,message,insertions,deletions,patch_id,\
+test_insertions,test_deletions,production_insertions,production_deletions,\
+docs_insertions,docs_deletions,config_insertions,config_deletions
+api,2024-03-01 10:00:00,480,release,c3,peter,fix,5,1,p1,2,0,3,1,0,0,0,0
+api,2024-02-01 10:00:00,480,main,c1,peter,fix,5,1,p1,2,0,3,1,0,0,0,0
+api-fork,2024-02-02 10:00:00,0,main,c2,bob,fix,5,1,p1,2,0,3,1,0,0,0,0
+api,2024-02-03 10:00:00,-300,main,c4,bob,feat,10,0,p2,0,0,0,0,10,0,0,0
+";
+        CsvReadOptions::default()
+            .with_has_header(true)
+            .map_parse_options(|s| s.with_try_parse_dates(true))
+            .into_reader_with_file_handle(std::io::Cursor::new(content))
+            .finish()
+            .unwrap()
     }
-    debug!("filter options: {:?}", filter_options);
-    let my_df = MyDataFrame::new(&df, filter_options);
-    let summ = my_df.summary();
 
-    let out_type = OutputType::from_str(args.format.as_str()).unwrap();
-    get_output(out_type, summ).output().expect("output failed");
+    pub(crate) fn files_df() -> DataFrame {
+        let content = "\
+repo,branch,commit_id,path,category,insertions,deletions
+api,release,c3,api/user.go,production,3,1
+api,release,c3,api/user_test.go,test,2,0
+api,main,c1,api/user.go,production,3,1
+api,main,c1,api/user_test.go,test,2,0
+api-fork,main,c2,api/user.go,production,3,1
+api-fork,main,c2,api/user_test.go,test,2,0
+api,main,c4,README.md,docs,10,0
+";
+        read_cursor(std::io::Cursor::new(content.as_bytes().to_vec()))
+    }
+
+    pub(crate) fn filter_options(dedup: bool) -> FilterOptions {
+        let authors = ["peter", "bob"].map(|name| config::Author {
+            name: name.to_string(),
+            alias: vec![],
+        });
+        FilterOptions {
+            since: None,
+            until: None,
+            authors: identity::IdentityResolver::new(&authors, &[]),
+            teams: team::TeamResolver::default(),
+            repo_filters: vec![],
+            dedup,
+            business_hours: config::BusinessHours::default(),
+            outliers: OutlierMode::Keep,
+            tz: "utc".parse().unwrap(),
+        }
+    }
+
+    fn total_insertions(df: &DataFrame) -> i64 {
+        df.column("insertions").unwrap().sum::<i64>().unwrap()
+    }
+
+    #[test]
+    fn test_summary_dedup() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        assert_eq!(
+            total_insertions(&MyDataFrame::new(&df, &opts).summary()),
+            25
+        );
+
+        let opts = filter_options(true);
+        let summ = MyDataFrame::new(&df, &opts).summary();
+        assert_eq!(total_insertions(&summ), 15);
+        // the earliest occurrence (main, 2024-02-01) owns the change
+        let branches: Vec<_> = summ
+            .column("branch")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert!(!branches.contains(&"release"));
+    }
+
+    #[test]
+    fn test_dedup_after_repo_filters() {
+        let df = detail_df();
+        let config = config::Config::parse(
+            r#"repos:
+  - url: https://github.com/group/api.git
+    name: api
+    branches: [main]
+    pathspec: []
+    since: 2024-02-15
+"#,
+            std::path::Path::new("x.yml"),
+        )
+        .unwrap();
+        let mut opts = filter_options(true);
+        opts.repo_filters = repo_filter::RepoFilter::from_repos(&config.repos);
+        // c1 (api, 2024-02-01) is before the repo window, the fork copy c2 is the earliest counted one
+        let summ = MyDataFrame::new(&df, &opts).summary();
+        assert_eq!(total_insertions(&summ), 5);
+        let repos: Vec<_> = summ
+            .column("repo")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(repos, ["api-fork"]);
+    }
+
+    #[test]
+    fn test_summary_categories() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        let summ = MyDataFrame::new(&df, &opts)
+            .summary()
+            .lazy()
+            .filter(
+                col("repo")
+                    .eq(lit("api"))
+                    .and(col("branch").eq(lit("main"))),
+            )
+            .sort(["author"], SortMultipleOptions::default())
+            .collect()
+            .unwrap();
+        // bob only changed docs, no production change => no ratio
+        let ratio = summ.column("test_ratio").unwrap().f64().unwrap();
+        assert_eq!(ratio.get(0), None);
+        assert_eq!(ratio.get(1), Some(0.5));
+        let docs = summ.column("docs_insertions").unwrap().i64().unwrap();
+        assert_eq!(docs.get(0), Some(10));
+    }
+
+    #[test]
+    fn test_detail_path() {
+        assert_eq!(detail_path("detail.csv", "files"), "detail_files.csv");
+        assert_eq!(
+            detail_path("out/2024q1.csv", "files"),
+            "out/2024q1_files.csv"
+        );
+        assert_eq!(detail_path("detail.csv", "merges"), "detail_merges.csv");
+    }
+
+    #[test]
+    fn test_patch_groups() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        let groups = MyDataFrame::new(&df, &opts).patch_groups();
+        assert_eq!(groups.height(), 1);
+        assert_eq!(
+            groups
+                .column("first_commit_id")
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(0),
+            Some("c1")
+        );
+        assert_eq!(
+            groups.column("count").unwrap().u32().unwrap().get(0),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_filtered_identities() {
+        let content = "\
+repo,date,branch,commit_id,author,email,insertions,deletions
+api,2023-06-30 23:00:00,main,c1,pc,pc@home.net,1,0
+api,2023-07-01 01:00:00,main,c2,pc,pc@home.net,2,0
+api,2023-07-02 10:00:00,main,c3,Peter Chen,peter@corp.com,4,0
+api,2023-07-03 10:00:00,main,c4,Chenxi,chenxi@corp.com,8,0
+";
+        let df = CsvReadOptions::default()
+            .with_has_header(true)
+            .map_parse_options(|s| s.with_try_parse_dates(true))
+            .into_reader_with_file_handle(std::io::Cursor::new(content))
+            .finish()
+            .unwrap();
+        let config = config::Config::parse(
+            r#"authors:
+  - name: peter
+    alias: [Chen, { email: peter@corp.com }, { name: pc, until: 2023-06-30 }]
+  - name: bob
+    alias: [{ name: pc, since: 2023-07-01 }]
+"#,
+            std::path::Path::new("x.yml"),
+        )
+        .unwrap();
+        let mut opts = filter_options(false);
+        opts.authors = identity::IdentityResolver::new(&config.authors, &config.bots);
+        let filtered = MyDataFrame::new(&df, &opts).filtered().collect().unwrap();
+        let authors: Vec<_> = filtered
+            .column("author")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(authors, ["peter", "bob", "peter"]);
+    }
+
+    #[test]
+    fn test_summary_teams() {
+        let df = detail_df();
+        let config = config::Config::parse(
+            r#"authors: [{ name: peter }, { name: bob }]
+teams:
+  - name: platform
+    members: [peter]
+    teams:
+      - name: infra
+        members: [{ author: bob, until: 2024-02-02 }]
+  - name: product
+    members: [{ author: bob, since: 2024-02-03 }]
+"#,
+            std::path::Path::new("x.yml"),
+        )
+        .unwrap();
+        let mut opts = filter_options(false);
+        opts.teams = team::TeamResolver::new(&config.teams);
+        let summary = MyDataFrame::new(&df, &opts).summary();
+        let column = |name: &str| -> Vec<Option<String>> {
+            summary
+                .column(name)
+                .unwrap()
+                .cast(&DataType::String)
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_iter()
+                .map(|v| v.map(String::from))
+                .collect()
+        };
+        let row = |team: &str, author: Option<&str>| -> String {
+            format!("{}:{}", team, author.unwrap_or("*"))
+        };
+        let rows: Vec<String> = column("team")
+            .iter()
+            .zip(column("author"))
+            .map(|(t, a)| row(t.as_deref().unwrap(), a.as_deref()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "platform:*",
+                "platform:peter",
+                "platform:peter",
+                "platform/infra:*",
+                "platform/infra:bob",
+                "product:*",
+                "product:bob",
+            ]
+        );
+        assert_eq!(column("org")[3].as_deref(), Some("platform"));
+        // 小计包括下级团队
+        assert_eq!(column("insertions")[0].as_deref(), Some("15"));
+        assert_eq!(column("test_ratio")[0].as_deref(), Some("0.5"));
+    }
+
+    #[test]
+    fn test_filtered_repo_filters() {
+        let content = "\
+repo,date,branch,commit_id,author,email,message,insertions,deletions
+api,2019-12-31 23:00:00,main,c1,peter,peter@corp.com,Imported from SVN r1,1,0
+api,2020-01-01 01:00:00,main,c2,peter,peter@corp.com,fix,2,0
+api,2020-01-02 10:00:00,main,c3,svn-import,peter@corp.com,fix,4,0
+api,2020-01-03 10:00:00,main,c4,peter,ci@CI.corp.com,release,8,0
+api,2020-01-04 10:00:00,main,c5,dependabot[bot],x@users.noreply.github.com,bump,16,0
+web,2019-12-31 23:00:00,main,c6,peter,peter@corp.com,Imported from SVN r2,32,0
+";
+        let df = CsvReadOptions::default()
+            .with_has_header(true)
+            .map_parse_options(|s| s.with_try_parse_dates(true))
+            .into_reader_with_file_handle(std::io::Cursor::new(content))
+            .finish()
+            .unwrap();
+        let config = config::Config::parse(
+            r#"authors: [{ name: peter, alias: [svn-import] }]
+bots: [{ regex: "\\[bot\\]$" }]
+repos:
+  - url: https://github.com/group/api.git
+    name: api
+    branches: [main]
+    pathspec: []
+    since: 2020-01-01
+    exclude_authors: [svn-import]
+    exclude_emails: ["@ci\\.corp\\.com$"]
+    ignore_messages: ["^Imported from SVN"]
+"#,
+            std::path::Path::new("x.yml"),
+        )
+        .unwrap();
+        let mut opts = filter_options(false);
+        opts.authors = identity::IdentityResolver::new(&config.authors, &config.bots);
+        opts.repo_filters = repo_filter::RepoFilter::from_repos(&config.repos);
+        let filtered = MyDataFrame::new(&df, &opts)
+            .filtered()
+            .sort(["commit_id"], SortMultipleOptions::default())
+            .collect()
+            .unwrap();
+        let ids: Vec<_> = filtered
+            .column("commit_id")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        // 其他repo不受影响，机器人单独归类而不是丢弃
+        assert_eq!(ids, ["c2", "c5", "c6"]);
+        assert_eq!(
+            filtered.column("author").unwrap().str().unwrap().get(1),
+            Some(identity::BOTS)
+        );
+        assert!(filtered.column("git_author").is_err());
+    }
 }
@@ -0,0 +1,257 @@
+use crate::{repo_filter, MyDataFrame};
+use polars::prelude::*;
+
+impl MyDataFrame<'_> {
+    /// 合并(MR/PR)列表：merger按作者alias合并，按合并时间和repo的过滤条件过滤，`cycle_hours` 是第一个commit到合并的小时数
+    ///
+    /// 没有合并的detail时返回错误
+    pub fn filtered_merges(&self) -> Result<LazyFrame, String> {
+        let Some(merges) = self.merges else {
+            return Err(
+                "no merges detail, parse repos again or provide <source>_merges.csv".to_string(),
+            );
+        };
+        let mut q = merges.clone().lazy();
+        // 没有合并时csv只有表头，时间列会被识别成字符串
+        for column in ["merged_at", "first_commit_at"] {
+            if merges
+                .column(column)
+                .is_ok_and(|c| c.dtype() == &DataType::String)
+            {
+                q = q.with_column(col(column).str().to_datetime(
+                    Some(TimeUnit::Microseconds),
+                    None,
+                    StrptimeOptions::default(),
+                    lit("raise"),
+                ));
+            }
+        }
+        q = q.with_column(col("merger").alias("git_author"));
+        q = self.map_aliases(q, "merger", "merged_at");
+        // repo的过滤条件按commit的列名写，merge commit没有email
+        q = q
+            .with_columns([
+                col("merged_at").alias("date"),
+                col("merger").alias("author"),
+                col("title").alias("message"),
+            ])
+            .filter(repo_filter::excluded(&self.filter_options.repo_filters, false).not())
+            .drop(["git_author", "date", "author", "message"]);
+        if let Some(since) = self.filter_options.since {
+            q = q.filter(col("merged_at").gt_eq(lit(since.naive_utc())));
+        }
+        if let Some(until) = self.filter_options.until {
+            q = q.filter(col("merged_at").lt_eq(lit(until.naive_utc())));
+        }
+        Ok(q.with_column(
+            ((col("merged_at") - col("first_commit_at"))
+                .dt()
+                .total_seconds()
+                .cast(DataType::Float64)
+                / lit(3600.0))
+            .alias("cycle_hours"),
+        ))
+    }
+
+    /// 合并吞吐量和周期：每个repo/分支每月的合并数、合并的commit和行数、cycle time分位数
+    pub fn merge_requests(&self) -> Result<DataFrame, String> {
+        let quantile = |p: f64, name: &str| {
+            col("cycle_hours")
+                .quantile(lit(p), QuantileInterpolOptions::Linear)
+                .alias(name)
+        };
+        Ok(self
+            .filtered_merges()?
+            .with_column(
+                self.report_time("merged_at")
+                    .dt()
+                    .strftime("%Y-%m")
+                    .alias("month"),
+            )
+            .group_by([col("repo"), col("branch"), col("month")])
+            .agg([
+                len().alias("merges"),
+                col("mr_id")
+                    .is_not_null()
+                    .cast(DataType::UInt32)
+                    .sum()
+                    .alias("with_mr_id"),
+                col("merger").n_unique().alias("mergers"),
+                col("commits").sum(),
+                (col("insertions") + col("deletions")).sum().alias("lines"),
+                col("cycle_hours").mean().alias("cycle_hours_mean"),
+                quantile(0.5, "cycle_hours_p50"),
+                quantile(0.9, "cycle_hours_p90"),
+            ])
+            .sort(["repo", "branch", "month"], SortMultipleOptions::default())
+            .collect()
+            .unwrap())
+    }
+
+    /// 所有合并，最近的在前
+    pub fn merge_list(&self) -> Result<DataFrame, String> {
+        Ok(self
+            .filtered_merges()?
+            .select([
+                col("repo"),
+                col("branch"),
+                col("mr_id"),
+                col("title"),
+                col("source_branch"),
+                col("merger"),
+                self.report_time("merged_at"),
+                col("commits"),
+                (col("insertions") + col("deletions")).alias("lines"),
+                col("cycle_hours"),
+            ])
+            .sort(
+                ["merged_at"],
+                SortMultipleOptions::default().with_order_descending(true),
+            )
+            .collect()
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{detail_df, filter_options};
+    use crate::{read_cursor, MyDataFrame};
+    use polars::prelude::*;
+
+    fn merges_df() -> DataFrame {
+        let content = "\
+repo,branch,commit_id,mr_id,title,source_branch,target_branch,merger,merged_at,first_commit_at,commits,insertions,deletions
+api,main,m1,!1,Add login,login,main,peter,2024-02-01 12:00:00,2024-01-31 12:00:00,2,10,2
+api,main,m2,!2,Fix login,fix,main,peter,2024-02-10 12:00:00,2024-02-10 06:00:00,1,1,1
+api,main,m3,,,release,main,bob,2024-03-01 12:00:00,2024-02-20 12:00:00,5,100,0
+";
+        read_cursor(std::io::Cursor::new(content.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_merge_requests() {
+        let df = detail_df();
+        let merges = merges_df();
+        let opts = filter_options(false);
+        let my_df = MyDataFrame::new(&df, &opts).with_merges(&merges);
+
+        let report = my_df.merge_requests().unwrap();
+        assert_eq!(report.height(), 2);
+        let month = report.column("month").unwrap().str().unwrap();
+        assert_eq!(month.get(0), Some("2024-02"));
+        let count = report.column("merges").unwrap().u32().unwrap();
+        assert_eq!(count.get(0), Some(2));
+        let with_id = report.column("with_mr_id").unwrap().u32().unwrap();
+        assert_eq!(with_id.get(1), Some(0));
+        let p50 = report.column("cycle_hours_p50").unwrap().f64().unwrap();
+        // 24h and 6h
+        assert_eq!(p50.get(0), Some(15.0));
+
+        let list = my_df.merge_list().unwrap();
+        let cycle = list.column("cycle_hours").unwrap().f64().unwrap();
+        assert_eq!(cycle.get(0), Some(240.0));
+
+        // no merges detail
+        let err = MyDataFrame::new(&df, &opts).merge_list().unwrap_err();
+        assert!(err.starts_with("no merges detail"));
+    }
+
+    #[test]
+    fn test_merges_report_tz() {
+        let df = detail_df();
+        let content = "\
+repo,branch,commit_id,mr_id,title,source_branch,target_branch,merger,merged_at,first_commit_at,commits,insertions,deletions
+api,main,m1,!1,Add login,login,main,peter,2024-02-29 13:00:00,2024-02-29 12:00:00,1,1,0
+";
+        let merges = read_cursor(std::io::Cursor::new(content.as_bytes().to_vec()));
+        let mut opts = filter_options(false);
+        opts.tz = "+12:00".parse().unwrap();
+        let my_df = MyDataFrame::new(&df, &opts).with_merges(&merges);
+
+        let report = my_df.merge_requests().unwrap();
+        let month = report.column("month").unwrap().str().unwrap();
+        assert_eq!(month.get(0), Some("2024-03"));
+        let list = my_df.merge_list().unwrap();
+        let merged_at = list.column("merged_at").unwrap().cast(&DataType::String);
+        assert_eq!(
+            merged_at.unwrap().str().unwrap().get(0),
+            Some("2024-03-01 01:00:00.000000")
+        );
+        let cycle = list.column("cycle_hours").unwrap().f64().unwrap();
+        assert_eq!(cycle.get(0), Some(1.0));
+    }
+
+    #[test]
+    fn test_merger_aliases_by_merge_date() {
+        let df = detail_df();
+        let merges = merges_df().lazy().with_column(lit("pc").alias("merger"));
+        let merges = merges.collect().unwrap();
+        let config = config::Config::parse(
+            r#"authors:
+  - { name: peter, alias: [{ name: pc, until: 2024-02-05 }] }
+  - { name: bob, alias: [{ name: pc, since: 2024-02-06 }] }
+"#,
+            std::path::Path::new("x.yml"),
+        )
+        .unwrap();
+        let mut opts = filter_options(false);
+        opts.authors = crate::identity::IdentityResolver::new(&config.authors, &[]);
+        let list = MyDataFrame::new(&df, &opts)
+            .with_merges(&merges)
+            .merge_list()
+            .unwrap();
+        let mergers: Vec<_> = list
+            .column("merger")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        // newest first
+        assert_eq!(mergers, ["bob", "bob", "peter"]);
+    }
+
+    #[test]
+    fn test_merges_repo_filters() {
+        let df = detail_df();
+        let merges = merges_df();
+        let merge_ids = |filter: &str| {
+            let config = config::Config::parse(
+                &format!(
+                    r#"repos:
+  - url: https://github.com/group/api.git
+    name: api
+    branches: [main]
+    pathspec: []
+    {}
+"#,
+                    filter
+                ),
+                std::path::Path::new("x.yml"),
+            )
+            .unwrap();
+            let mut opts = filter_options(false);
+            opts.repo_filters = crate::repo_filter::RepoFilter::from_repos(&config.repos);
+            let list = MyDataFrame::new(&df, &opts)
+                .with_merges(&merges)
+                .filtered_merges()
+                .unwrap()
+                .collect()
+                .unwrap();
+            let mut ids: Vec<String> = list
+                .column("commit_id")
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_no_null_iter()
+                .map(String::from)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(merge_ids("exclude_authors: [peter]"), ["m3"]);
+        assert_eq!(merge_ids("until: 2024-02-15"), ["m1", "m2"]);
+        assert_eq!(merge_ids("ignore_messages: [\"^Fix\"]"), ["m1", "m3"]);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::MyDataFrame;
+use config::OutlierOptions;
+use polars::prelude::*;
+
+/// summary中如何处理超大commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutlierMode {
+    #[default]
+    Keep,
+    /// 按比例缩小到阈值
+    Cap,
+    Exclude,
+}
+
+impl OutlierMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "keep" => Some(OutlierMode::Keep),
+            "cap" => Some(OutlierMode::Cap),
+            "exclude" => Some(OutlierMode::Exclude),
+            _ => None,
+        }
+    }
+}
+
+fn commit_size() -> Expr {
+    col("insertions") + col("deletions")
+}
+
+/// detail中标记超大commit: `outlier`、`outlier_reason`、`outlier_limit`(触发的最小阈值)
+///
+/// 阈值是固定行数`max_lines`，或者所在repo的commit大小分位数`percentile`
+pub fn flag_outliers(df: DataFrame, options: &OutlierOptions) -> PolarsResult<DataFrame> {
+    let size = commit_size();
+    let mut limits: Vec<(Expr, Expr)> = vec![];
+    if let Some(max_lines) = options.max_lines {
+        let limit = lit(max_lines as i64);
+        let reason = concat_str(
+            [
+                lit("size "),
+                size.clone().cast(DataType::String),
+                lit(format!(" > max_lines {}", max_lines)),
+            ],
+            "",
+            true,
+        );
+        limits.push((limit, reason));
+    }
+    if let Some(percentile) = options.percentile {
+        polars_ensure!(
+            percentile > 0.0 && percentile <= 1.0,
+            InvalidOperation: "outliers.percentile {} should be in (0, 1]", percentile
+        );
+        let limit = size
+            .clone()
+            .quantile(lit(percentile), QuantileInterpolOptions::Linear)
+            .over([col("repo")])
+            .cast(DataType::Int64);
+        let reason = concat_str(
+            [
+                lit("size "),
+                size.clone().cast(DataType::String),
+                lit(format!(" > p{} of repo ", percentile * 100.0)),
+                limit.clone().cast(DataType::String),
+            ],
+            "",
+            true,
+        );
+        limits.push((limit, reason));
+    }
+
+    let mut limit_expr = lit(NULL).cast(DataType::Int64);
+    let mut reason_expr = lit(NULL).cast(DataType::String);
+    // 取最小的阈值，原因按配置顺序取第一个触发的
+    for (limit, reason) in limits.into_iter().rev() {
+        let exceeded = size.clone().gt(limit.clone());
+        reason_expr = when(exceeded.clone()).then(reason).otherwise(reason_expr);
+        limit_expr = when(
+            exceeded.and(
+                limit_expr
+                    .clone()
+                    .is_null()
+                    .or(limit.clone().lt(limit_expr.clone())),
+            ),
+        )
+        .then(limit)
+        .otherwise(limit_expr);
+    }
+
+    df.lazy()
+        .with_columns([
+            limit_expr.clone().is_not_null().alias("outlier"),
+            reason_expr.alias("outlier_reason"),
+            limit_expr.alias("outlier_limit"),
+        ])
+        .collect()
+}
+
+/// summary前按`OutlierMode`处理被标记的commit，没有标记列时原样返回
+///
+/// Cap把insertions/deletions按同一比例缩小，两者之和正好是`outlier_limit`；
+/// `columns` 中各分类的 `<category>_insertions/deletions` 按累计值从缩小后的总数分配，分类之和仍等于总数
+pub fn apply_outlier_mode(q: LazyFrame, mode: OutlierMode, columns: &[&str]) -> LazyFrame {
+    match mode {
+        OutlierMode::Keep => q,
+        OutlierMode::Exclude => q.filter(col("outlier").not()),
+        OutlierMode::Cap => {
+            let ratio = col("outlier_limit").cast(DataType::Float64)
+                / commit_size().cast(DataType::Float64);
+            let insertions = when(col("outlier"))
+                .then(
+                    (col("insertions").cast(DataType::Float64) * ratio)
+                        .round(0)
+                        .cast(DataType::Int64),
+                )
+                .otherwise(col("insertions").cast(DataType::Int64));
+            let deletions = when(col("outlier"))
+                .then(col("outlier_limit") - insertions.clone())
+                .otherwise(col("deletions").cast(DataType::Int64));
+
+            let mut scaled = vec![
+                insertions.clone().alias("insertions"),
+                deletions.clone().alias("deletions"),
+            ];
+            for (kind, capped_total) in [("insertions", insertions), ("deletions", deletions)] {
+                let suffix = format!("_{}", kind);
+                let total = col(kind).cast(DataType::Float64);
+                let mut cumulative = lit(0i64);
+                let mut previous = lit(0i64);
+                for c in columns.iter().filter(|c| c.ends_with(&suffix)) {
+                    cumulative = cumulative + col(c);
+                    let capped = when(col(kind).gt(lit(0)))
+                        .then(
+                            (cumulative.clone().cast(DataType::Float64)
+                                * capped_total.clone().cast(DataType::Float64)
+                                / total.clone())
+                            .round(0)
+                            .cast(DataType::Int64),
+                        )
+                        .otherwise(lit(0i64));
+                    scaled.push((capped.clone() - previous).alias(c));
+                    previous = capped;
+                }
+            }
+            q.with_columns(scaled)
+        }
+    }
+}
+
+impl MyDataFrame<'_> {
+    /// commit大小(insertions + deletions)的分位数，分别按author和repo
+    pub fn size_distribution(&self) -> DataFrame {
+        let q = self
+            .filtered()
+            .unique(
+                Some(vec!["repo".to_string(), "commit_id".to_string()]),
+                UniqueKeepStrategy::First,
+            )
+            .with_column(commit_size().alias("size"));
+        let quantile = |p: f64, name: &str| {
+            col("size")
+                .quantile(lit(p), QuantileInterpolOptions::Linear)
+                .alias(name)
+        };
+        let aggs = [
+            col("size").count().alias("commits"),
+            quantile(0.5, "p50"),
+            quantile(0.75, "p75"),
+            quantile(0.9, "p90"),
+            quantile(0.95, "p95"),
+            quantile(0.99, "p99"),
+            col("size").max().alias("max"),
+        ];
+        let by = |level: &str, column: &str| {
+            q.clone()
+                .group_by([col(column).alias("name")])
+                .agg(aggs.clone())
+                .with_column(lit(level).alias("level"))
+        };
+        concat(
+            [by("author", "author"), by("repo", "repo")],
+            UnionArgs::default(),
+        )
+        .unwrap()
+        .select([col("level"), col("*").exclude(["level"])])
+        .sort(["level", "name"], SortMultipleOptions::default())
+        .collect()
+        .unwrap()
+    }
+
+    /// 被标记的超大commit，供人工检查
+    pub fn outliers(&self) -> DataFrame {
+        if !self.has_column("outlier") {
+            return DataFrame::empty();
+        }
+        self.filtered()
+            .filter(col("outlier"))
+            .select([
+                col("repo"),
+                col("branch"),
+                self.report_time("date"),
+                col("commit_id"),
+                col("author"),
+                commit_size().alias("size"),
+                col("outlier_reason"),
+                col("message")
+                    .str()
+                    .split(lit("\n"))
+                    .list()
+                    .first()
+                    .alias("subject"),
+            ])
+            .sort(
+                ["size"],
+                SortMultipleOptions::default().with_order_descending(true),
+            )
+            .collect()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{detail_df, filter_options};
+
+    fn flagged(max_lines: Option<u64>, percentile: Option<f64>) -> DataFrame {
+        let options = OutlierOptions {
+            max_lines,
+            percentile,
+        };
+        flag_outliers(detail_df(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_flag_outliers_max_lines() {
+        let df = flagged(Some(8), None);
+        let outlier = df.column("outlier").unwrap().bool().unwrap();
+        let commits = df.column("commit_id").unwrap().str().unwrap();
+        let flagged: Vec<_> = commits
+            .into_iter()
+            .zip(outlier)
+            .filter(|(_, o)| *o == Some(true))
+            .map(|(c, _)| c.unwrap())
+            .collect();
+        assert_eq!(flagged, vec!["c4"]);
+        let reason = df.column("outlier_reason").unwrap().str().unwrap();
+        assert_eq!(reason.get(3), Some("size 10 > max_lines 8"));
+    }
+
+    #[test]
+    fn test_flag_outliers_percentile() {
+        // api: 6, 6, 10 => p50 is 6, api-fork has a single commit
+        let df = flagged(None, Some(0.5));
+        let outlier = df.column("outlier").unwrap().bool().unwrap();
+        assert_eq!(
+            outlier.into_iter().collect::<Vec<_>>(),
+            vec![Some(false), Some(false), Some(false), Some(true)]
+        );
+        let reason = df.column("outlier_reason").unwrap().str().unwrap();
+        assert_eq!(reason.get(3), Some("size 10 > p50 of repo 6"));
+    }
+
+    #[test]
+    fn test_flag_outliers_none() {
+        let df = flagged(None, None);
+        let outlier = df.column("outlier").unwrap().bool().unwrap();
+        assert!(outlier.into_iter().all(|o| o == Some(false)));
+    }
+
+    #[test]
+    fn test_flag_outliers_invalid_percentile() {
+        let options = OutlierOptions {
+            max_lines: None,
+            percentile: Some(99.0),
+        };
+        assert!(flag_outliers(detail_df(), &options).is_err());
+    }
+
+    #[test]
+    fn test_outlier_mode() {
+        let df = flagged(Some(5), None);
+        let mut opts = filter_options(false);
+        let total = |df: &DataFrame| df.column("insertions").unwrap().sum::<i64>().unwrap();
+        assert_eq!(total(&MyDataFrame::new(&df, &opts).summary()), 25);
+        opts.outliers = OutlierMode::Cap;
+        // c1, c2, c3: 6 lines capped to 5 => 5 * 5/6 ≈ 4 insertions, c4: 10 => 5
+        assert_eq!(total(&MyDataFrame::new(&df, &opts).summary()), 17);
+        opts.outliers = OutlierMode::Exclude;
+        assert_eq!(total(&MyDataFrame::new(&df, &opts).summary()), 0);
+    }
+
+    #[test]
+    fn test_cap_categories() {
+        let df = flagged(Some(5), None);
+        let mut opts = filter_options(false);
+        opts.outliers = OutlierMode::Cap;
+        let capped = MyDataFrame::new(&df, &opts).filtered().collect().unwrap();
+        let get = |c: &str, commit: &str| {
+            let commits = capped.column("commit_id").unwrap().str().unwrap();
+            let row = commits.into_iter().position(|c| c == Some(commit)).unwrap();
+            capped.column(c).unwrap().i64().unwrap().get(row)
+        };
+        // c4: 10 docs lines capped to 5
+        assert_eq!(get("insertions", "c4"), Some(5));
+        assert_eq!(get("docs_insertions", "c4"), Some(5));
+        // c1: 5 + 1 lines capped to 4 + 1, test 2 and production 3 insertions share the 4
+        assert_eq!(get("insertions", "c1"), Some(4));
+        assert_eq!(get("deletions", "c1"), Some(1));
+        assert_eq!(get("test_insertions", "c1"), Some(2));
+        assert_eq!(get("production_insertions", "c1"), Some(2));
+        assert_eq!(get("production_deletions", "c1"), Some(1));
+    }
+
+    #[test]
+    fn test_cap_to_limit() {
+        let df = df!(
+            "insertions" => [1i64, 7, 3, 0],
+            "deletions" => [1i64, 5, 0, 0],
+            "test_insertions" => [1i64, 3, 1, 0],
+            "production_insertions" => [0i64, 4, 2, 0],
+            "outlier" => [true, true, false, false],
+            "outlier_limit" => [Some(1i64), Some(5), None, None],
+        )
+        .unwrap();
+        let columns = df.get_column_names();
+        let capped = apply_outlier_mode(df.clone().lazy(), OutlierMode::Cap, &columns)
+            .collect()
+            .unwrap();
+        let values = |c: &str| -> Vec<i64> {
+            let column = capped.column(c).unwrap().i64().unwrap();
+            column.into_no_null_iter().collect()
+        };
+        let insertions = values("insertions");
+        let deletions = values("deletions");
+        let size: Vec<i64> = insertions
+            .iter()
+            .zip(&deletions)
+            .map(|(i, d)| i + d)
+            .collect();
+        assert_eq!(size, [1, 5, 3, 0]);
+        let categories: Vec<i64> = values("test_insertions")
+            .iter()
+            .zip(values("production_insertions"))
+            .map(|(t, p)| t + p)
+            .collect();
+        assert_eq!(categories, insertions);
+    }
+
+    #[test]
+    fn test_outliers_and_size_distribution() {
+        let df = flagged(Some(8), None);
+        let opts = filter_options(false);
+        let my_df = MyDataFrame::new(&df, &opts);
+        let outliers = my_df.outliers();
+        assert_eq!(outliers.height(), 1);
+        assert_eq!(
+            outliers.column("subject").unwrap().str().unwrap().get(0),
+            Some("feat")
+        );
+
+        let dist = my_df.size_distribution();
+        // authors bob, peter + repos api, api-fork
+        assert_eq!(dist.height(), 4);
+        let max = dist.column("max").unwrap().i64().unwrap();
+        let names = dist.column("name").unwrap().str().unwrap();
+        let bob = names.into_iter().position(|n| n == Some("bob")).unwrap();
+        assert_eq!(max.get(bob), Some(10));
+    }
+}
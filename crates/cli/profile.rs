@@ -0,0 +1,146 @@
+use crate::MyDataFrame;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// 每个作者最长的连续活跃天数，`days` 按 author、day 排序且去重
+fn longest_streaks(days: &DataFrame) -> HashMap<String, u32> {
+    let authors = days.column("author").unwrap().str().unwrap();
+    let dates = days.column("day").unwrap().cast(&DataType::Int32).unwrap();
+    let mut streaks: HashMap<String, u32> = HashMap::new();
+    let mut prev: Option<(&str, i32)> = None;
+    let mut current = 0;
+    for (author, day) in authors.into_iter().zip(dates.i32().unwrap()) {
+        let (Some(author), Some(day)) = (author, day) else {
+            continue;
+        };
+        current = match prev {
+            Some((a, d)) if a == author && d + 1 == day => current + 1,
+            _ => 1,
+        };
+        let longest = streaks.entry(author.to_string()).or_default();
+        *longest = (*longest).max(current);
+        prev = Some((author, day));
+    }
+    streaks
+}
+
+impl MyDataFrame<'_> {
+    /// 作者画像: 首次/最后一次commit、活跃天数、最长连续活跃天数、活跃日平均commit数、涉及repo数和主要repo
+    ///
+    /// 同一个commit出现在多个分支只算一次，活跃日按作者自己的时区
+    pub fn profile(&self) -> DataFrame {
+        let commits = self
+            .filtered()
+            .unique(
+                Some(vec!["repo".to_string(), "commit_id".to_string()]),
+                UniqueKeepStrategy::First,
+            )
+            .with_column(self.author_local_date().dt().date().alias("day"));
+
+        // commit最多的repo，数量相同时按名字
+        let primary = commits
+            .clone()
+            .group_by([col("author"), col("repo")])
+            .agg([len().alias("repo_commits")])
+            .sort(
+                ["author", "repo_commits", "repo"],
+                SortMultipleOptions::default().with_order_descending_multi([false, true, false]),
+            )
+            .group_by([col("author")])
+            .agg([col("repo").first().alias("primary_repo")]);
+
+        let days = commits
+            .clone()
+            .select([col("author"), col("day")])
+            .unique(None, UniqueKeepStrategy::First)
+            .sort(["author", "day"], SortMultipleOptions::default())
+            .collect()
+            .unwrap();
+        let (authors, streaks): (Vec<String>, Vec<u32>) =
+            longest_streaks(&days).into_iter().unzip();
+        let streaks = DataFrame::new(vec![
+            Series::new("author", authors),
+            Series::new("longest_streak", streaks),
+        ])
+        .unwrap();
+
+        commits
+            .group_by([col("author")])
+            .agg([
+                self.report_time("date").min().alias("first_commit"),
+                self.report_time("date").max().alias("last_commit"),
+                len().alias("commits"),
+                col("day").n_unique().alias("active_days"),
+                col("repo").n_unique().alias("repos"),
+            ])
+            .with_column(
+                (col("commits").cast(DataType::Float64)
+                    / col("active_days").cast(DataType::Float64))
+                .alias("commits_per_day"),
+            )
+            .join(
+                streaks.lazy(),
+                [col("author")],
+                [col("author")],
+                JoinArgs::new(JoinType::Left),
+            )
+            .join(
+                primary,
+                [col("author")],
+                [col("author")],
+                JoinArgs::new(JoinType::Left),
+            )
+            .select([
+                col("author"),
+                col("first_commit"),
+                col("last_commit"),
+                col("commits"),
+                col("active_days"),
+                col("longest_streak"),
+                col("commits_per_day"),
+                col("repos"),
+                col("primary_repo"),
+            ])
+            .sort(
+                ["commits", "author"],
+                SortMultipleOptions::default().with_order_descending_multi([true, false]),
+            )
+            .collect()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{detail_df, filter_options};
+    use crate::MyDataFrame;
+
+    #[test]
+    fn test_profile() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        let profile = MyDataFrame::new(&df, &opts).profile();
+        assert_eq!(profile.height(), 2);
+
+        let authors = profile.column("author").unwrap().str().unwrap();
+        let bob = authors.into_iter().position(|a| a == Some("bob")).unwrap();
+        let peter = authors
+            .into_iter()
+            .position(|a| a == Some("peter"))
+            .unwrap();
+        let streak = profile.column("longest_streak").unwrap().u32().unwrap();
+        // bob: 2024-02-02 (UTC) and 2024-02-03 05:00 (-05:00 => 2024-02-03)
+        assert_eq!(streak.get(bob), Some(2));
+        assert_eq!(streak.get(peter), Some(1));
+        let active_days = profile.column("active_days").unwrap().u32().unwrap();
+        assert_eq!(active_days.get(peter), Some(2));
+        let repos = profile.column("repos").unwrap().u32().unwrap();
+        assert_eq!(repos.get(bob), Some(2));
+        let primary = profile.column("primary_repo").unwrap().str().unwrap();
+        // one commit each in api and api-fork => by name
+        assert_eq!(primary.get(bob), Some("api"));
+        assert_eq!(primary.get(peter), Some("api"));
+        let per_day = profile.column("commits_per_day").unwrap().f64().unwrap();
+        assert_eq!(per_day.get(peter), Some(1.0));
+    }
+}
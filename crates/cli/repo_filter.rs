@@ -0,0 +1,77 @@
+use chrono::{Duration, NaiveDate};
+use polars::prelude::*;
+
+/// 一个repo自己的过滤条件，见 `config::Repo` 的 since/until/exclude_authors/exclude_emails/ignore_messages
+#[derive(Debug, Clone)]
+pub struct RepoFilter {
+    repo: String,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    exclude_authors: Vec<String>,
+    exclude_emails: Vec<String>,
+    ignore_messages: Vec<String>,
+}
+
+impl RepoFilter {
+    /// 只保留设置了过滤条件的repo
+    pub fn from_repos(repos: &[config::Repo]) -> Vec<RepoFilter> {
+        repos
+            .iter()
+            .map(|repo| RepoFilter {
+                repo: repo.repo_name(),
+                since: repo.since,
+                until: repo.until,
+                exclude_authors: repo.exclude_authors.clone(),
+                exclude_emails: repo.exclude_emails.clone(),
+                ignore_messages: repo.ignore_messages.clone(),
+            })
+            .filter(|f| {
+                f.since.is_some()
+                    || f.until.is_some()
+                    || !f.exclude_authors.is_empty()
+                    || !f.exclude_emails.is_empty()
+                    || !f.ignore_messages.is_empty()
+            })
+            .collect()
+    }
+
+    pub fn has_email_patterns(&self) -> bool {
+        !self.exclude_emails.is_empty()
+    }
+
+    /// 这个repo中要排除的commit，`git_author` 列是身份解析前git中的作者名
+    fn excluded(&self, with_email: bool) -> Expr {
+        let mut expr = lit(false);
+        // detail中的date是UTC，since/until是UTC日期
+        if let Some(since) = self.since {
+            expr = expr.or(col("date").lt(lit(since.and_hms_opt(0, 0, 0).unwrap())));
+        }
+        if let Some(until) = self.until {
+            let next_day = (until + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap();
+            expr = expr.or(col("date").gt_eq(lit(next_day)));
+        }
+        for author in &self.exclude_authors {
+            expr = expr
+                .or(col("git_author").eq(lit(author.as_str())))
+                .or(col("author").eq(lit(author.as_str())));
+        }
+        if with_email {
+            for pattern in &self.exclude_emails {
+                let pattern = format!("(?i){}", pattern);
+                expr = expr.or(col("email").str().contains(lit(pattern), false));
+            }
+        }
+        for pattern in &self.ignore_messages {
+            expr = expr.or(col("message").str().contains(lit(pattern.as_str()), false));
+        }
+        col("repo").eq(lit(self.repo.as_str())).and(expr)
+    }
+}
+
+/// 任一repo的过滤条件排除的commit，没有email列时不检查email
+pub fn excluded(filters: &[RepoFilter], with_email: bool) -> Expr {
+    filters
+        .iter()
+        .fold(lit(false), |expr, f| expr.or(f.excluded(with_email)))
+        .fill_null(lit(false))
+}
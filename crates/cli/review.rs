@@ -0,0 +1,450 @@
+//! gitlab/github导出的MR元数据(reviewer、approval、label、评论数)，通过commit SHA关联到commit detail
+//!
+//! 支持的导出格式，JSON数组:
+//! - gitlab: `GET /projects/:id/merge_requests` 的结果，可以在每个MR中加上
+//!   `/merge_requests/:iid/approvals` 返回的 `approved_by`
+//! - github: `GET /repos/:owner/:repo/pulls?state=closed` 的结果，可以在每个PR中加上
+//!   `/pulls/:number/reviews` 返回的数组作为 `reviews`
+
+use crate::MyDataFrame;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use config::Repo;
+use log::info;
+use polars::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+
+#[derive(Debug, Deserialize)]
+struct GitLabUser {
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabApproval {
+    user: GitLabUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabMr {
+    iid: u64,
+    title: String,
+    author: GitLabUser,
+    merge_commit_sha: Option<String>,
+    squash_commit_sha: Option<String>,
+    sha: Option<String>,
+    merged_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    reviewers: Vec<GitLabUser>,
+    #[serde(default)]
+    approved_by: Vec<GitLabApproval>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    user_notes_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubUser {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubLabel {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubHead {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubReview {
+    /// 账号删除后为null
+    user: Option<GitHubUser>,
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubPr {
+    number: u64,
+    title: String,
+    user: GitHubUser,
+    merge_commit_sha: Option<String>,
+    head: Option<GitHubHead>,
+    merged_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    requested_reviewers: Vec<GitHubUser>,
+    #[serde(default)]
+    reviews: Vec<GitHubReview>,
+    #[serde(default)]
+    labels: Vec<GitHubLabel>,
+    #[serde(default)]
+    comments: u32,
+    #[serde(default)]
+    review_comments: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExportedMr {
+    GitLab(GitLabMr),
+    GitHub(GitHubPr),
+}
+
+/// 统一后的MR元数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct MrMeta {
+    /// gitlab `!123`，github `#45`，和merge detail中的mr_id一致
+    pub mr_id: String,
+    pub title: String,
+    pub author: String,
+    /// merge commit、squash commit、源分支head，任意一个出现在detail中即关联上
+    pub shas: Vec<String>,
+    /// 参与review的人，包括approver，不包括MR作者
+    pub reviewers: Vec<String>,
+    pub approvers: Vec<String>,
+    pub labels: Vec<String>,
+    pub comments: u32,
+    /// 作者alias按合并时间匹配，没有合并的为None
+    pub merged_at: Option<NaiveDateTime>,
+}
+
+fn push_unique(people: &mut Vec<String>, person: String, author: &str) {
+    if person != author && !people.contains(&person) {
+        people.push(person);
+    }
+}
+
+impl From<ExportedMr> for MrMeta {
+    fn from(mr: ExportedMr) -> Self {
+        match mr {
+            ExportedMr::GitLab(mr) => {
+                let author = mr.author.username;
+                let mut reviewers = vec![];
+                let mut approvers = vec![];
+                for user in mr.reviewers {
+                    push_unique(&mut reviewers, user.username, &author);
+                }
+                for approval in mr.approved_by {
+                    push_unique(&mut reviewers, approval.user.username.clone(), &author);
+                    push_unique(&mut approvers, approval.user.username, &author);
+                }
+                MrMeta {
+                    mr_id: format!("!{}", mr.iid),
+                    title: mr.title,
+                    shas: [mr.merge_commit_sha, mr.squash_commit_sha, mr.sha]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                    author,
+                    reviewers,
+                    approvers,
+                    labels: mr.labels,
+                    comments: mr.user_notes_count,
+                    merged_at: mr.merged_at.map(|d| d.naive_utc()),
+                }
+            }
+            ExportedMr::GitHub(pr) => {
+                let author = pr.user.login;
+                let mut reviewers = vec![];
+                let mut approvers = vec![];
+                for user in pr.requested_reviewers {
+                    push_unique(&mut reviewers, user.login, &author);
+                }
+                for review in pr.reviews {
+                    let Some(user) = review.user else {
+                        continue;
+                    };
+                    if review.state == "APPROVED" {
+                        push_unique(&mut approvers, user.login.clone(), &author);
+                    }
+                    push_unique(&mut reviewers, user.login, &author);
+                }
+                MrMeta {
+                    mr_id: format!("#{}", pr.number),
+                    title: pr.title,
+                    shas: [pr.merge_commit_sha, pr.head.map(|h| h.sha)]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                    author,
+                    reviewers,
+                    approvers,
+                    labels: pr.labels.into_iter().map(|l| l.name).collect(),
+                    comments: pr.comments + pr.review_comments,
+                    merged_at: pr.merged_at.map(|d| d.naive_utc()),
+                }
+            }
+        }
+    }
+}
+
+pub fn parse_export(content: &str) -> Result<Vec<MrMeta>, serde_json::Error> {
+    let mrs: Vec<ExportedMr> = serde_json::from_str(content)?;
+    Ok(mrs.into_iter().map(MrMeta::from).collect())
+}
+
+/// 导入的MR元数据
+pub struct MrMetadata {
+    /// 一行一个 MR + sha
+    pub mrs: DataFrame,
+    /// 一行一个 MR + 参与者 + 角色(reviewer/approver)
+    pub reviews: DataFrame,
+}
+
+impl MrMetadata {
+    pub fn new(repo_mrs: &[(String, Vec<MrMeta>)]) -> Self {
+        let (mut repo, mut mr_id, mut sha, mut title, mut author, mut labels, mut comments) =
+            (vec![], vec![], vec![], vec![], vec![], vec![], vec![]);
+        let mut merged_at = vec![];
+        let (mut r_repo, mut r_mr_id, mut person, mut role, mut r_merged_at) =
+            (vec![], vec![], vec![], vec![], vec![]);
+        for (repo_name, list) in repo_mrs {
+            for mr in list {
+                for s in &mr.shas {
+                    repo.push(repo_name.as_str());
+                    mr_id.push(mr.mr_id.as_str());
+                    sha.push(s.as_str());
+                    title.push(mr.title.as_str());
+                    author.push(mr.author.as_str());
+                    labels.push(mr.labels.join(","));
+                    comments.push(mr.comments);
+                    merged_at.push(mr.merged_at);
+                }
+                let roles = mr
+                    .reviewers
+                    .iter()
+                    .map(|p| (p, "reviewer"))
+                    .chain(mr.approvers.iter().map(|p| (p, "approver")));
+                for (p, r) in roles {
+                    r_repo.push(repo_name.as_str());
+                    r_mr_id.push(mr.mr_id.as_str());
+                    person.push(p.as_str());
+                    role.push(r);
+                    r_merged_at.push(mr.merged_at);
+                }
+            }
+        }
+        MrMetadata {
+            mrs: DataFrame::new(vec![
+                Series::new("repo", repo),
+                Series::new("mr_id", mr_id),
+                Series::new("sha", sha),
+                Series::new("title", title),
+                Series::new("mr_author", author),
+                Series::new("labels", labels),
+                Series::new("comments", comments),
+                Series::new("merged_at", merged_at),
+            ])
+            .unwrap(),
+            reviews: DataFrame::new(vec![
+                Series::new("repo", r_repo),
+                Series::new("mr_id", r_mr_id),
+                Series::new("person", person),
+                Series::new("role", role),
+                Series::new("merged_at", r_merged_at),
+            ])
+            .unwrap(),
+        }
+    }
+}
+
+/// 读取所有repo配置的`mr_export`，都没有配置时返回None
+pub fn load_mr_exports(repos: &[Repo]) -> Result<Option<MrMetadata>, Box<dyn Error>> {
+    let mut repo_mrs = vec![];
+    for repo in repos {
+        let Some(path) = &repo.mr_export else {
+            continue;
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("read mr export {} failed: {}", path, e))?;
+        let mrs = parse_export(&content)
+            .map_err(|e| format!("parse mr export {} failed: {}", path, e))?;
+        info!("mr export: {}, {} merge requests", path, mrs.len());
+        repo_mrs.push((repo.repo_name().to_string(), mrs));
+    }
+    if repo_mrs.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(MrMetadata::new(&repo_mrs)))
+}
+
+impl MyDataFrame<'_> {
+    /// 和统计范围内的commit或merge commit SHA关联上的MR，(repo, mr_id, mr_author, comments)
+    fn linked_mrs(&self, metadata: &MrMetadata) -> LazyFrame {
+        let mut shas = self.filtered().select([col("repo"), col("commit_id")]);
+        // 没有合并的detail时只按commit关联
+        if let Ok(merges) = self.filtered_merges() {
+            shas = concat(
+                [shas, merges.select([col("repo"), col("commit_id")])],
+                UnionArgs::default(),
+            )
+            .unwrap();
+        }
+        let shas = shas.unique(None, UniqueKeepStrategy::First);
+        let mrs = self.map_aliases(metadata.mrs.clone().lazy(), "mr_author", "merged_at");
+        mrs.join(
+            shas,
+            [col("repo"), col("sha")],
+            [col("repo"), col("commit_id")],
+            JoinArgs::new(JoinType::Inner),
+        )
+        .unique(
+            Some(vec!["repo".to_string(), "mr_id".to_string()]),
+            UniqueKeepStrategy::First,
+        )
+        .select([col("repo"), col("mr_id"), col("mr_author"), col("comments")])
+    }
+
+    /// review负载：每个人写的commit和行数，以及作为MR作者、reviewer、approver参与的MR数
+    pub fn review_load(&self, metadata: &MrMetadata) -> DataFrame {
+        let authored = self
+            .filtered()
+            .unique(
+                Some(vec!["repo".to_string(), "commit_id".to_string()]),
+                UniqueKeepStrategy::First,
+            )
+            .group_by([col("author").alias("name")])
+            .agg([
+                len().alias("commits"),
+                (col("insertions") + col("deletions")).sum().alias("lines"),
+            ]);
+        let linked = self.linked_mrs(metadata);
+        let mrs_authored = linked
+            .clone()
+            .group_by([col("mr_author").alias("name")])
+            .agg([
+                len().alias("mrs_authored"),
+                col("comments").sum().alias("comments_received"),
+            ]);
+        let reviews = self.map_aliases(metadata.reviews.clone().lazy(), "person", "merged_at");
+        let by_role = |role: &str, alias: &str| {
+            reviews
+                .clone()
+                .filter(col("role").eq(lit(role)))
+                .join(
+                    linked.clone(),
+                    [col("repo"), col("mr_id")],
+                    [col("repo"), col("mr_id")],
+                    JoinArgs::new(JoinType::Inner),
+                )
+                .group_by([col("person").alias("name")])
+                .agg([len().alias(alias)])
+        };
+        let full = |l: LazyFrame, r: LazyFrame| {
+            l.join(
+                r,
+                [col("name")],
+                [col("name")],
+                JoinArgs::new(JoinType::Full).with_coalesce(JoinCoalesce::CoalesceColumns),
+            )
+        };
+        let counts = [
+            "commits",
+            "lines",
+            "mrs_authored",
+            "comments_received",
+            "reviews",
+            "approvals",
+        ];
+        full(
+            full(full(authored, mrs_authored), by_role("reviewer", "reviews")),
+            by_role("approver", "approvals"),
+        )
+        .with_columns(
+            counts
+                .iter()
+                .map(|c| col(c).cast(DataType::Int64).fill_null(lit(0i64)))
+                .collect::<Vec<_>>(),
+        )
+        .sort(
+            ["reviews", "lines"],
+            SortMultipleOptions::default().with_order_descending(true),
+        )
+        .collect()
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{detail_df, filter_options};
+
+    const GITLAB: &str = r#"[
+      {"iid": 12, "title": "Add login", "author": {"username": "peter", "name": "Peter"},
+       "merge_commit_sha": null, "squash_commit_sha": "c1", "sha": "h1",
+       "merged_at": "2024-02-01T12:00:00.000Z",
+       "reviewers": [{"username": "bob"}], "labels": ["backend"], "user_notes_count": 3,
+       "approved_by": [{"user": {"username": "bob"}}, {"user": {"username": "alice"}}]},
+      {"iid": 13, "title": "Not in detail", "author": {"username": "bob"},
+       "merge_commit_sha": "zzz", "reviewers": [{"username": "peter"}]}
+    ]"#;
+
+    const GITHUB: &str = r#"[
+      {"number": 45, "title": "Fix typo", "user": {"login": "bob"},
+       "merge_commit_sha": "c4", "head": {"sha": "h4"},
+       "requested_reviewers": [{"login": "carol"}], "labels": [{"name": "docs"}],
+       "comments": 1, "review_comments": 2,
+       "reviews": [{"user": {"login": "peter"}, "state": "APPROVED"},
+                   {"user": {"login": "bob"}, "state": "COMMENTED"},
+                   {"user": null, "state": "COMMENTED"}]}
+    ]"#;
+
+    #[test]
+    fn test_parse_export() {
+        let gitlab = parse_export(GITLAB).unwrap();
+        assert_eq!(gitlab[0].mr_id, "!12");
+        assert_eq!(gitlab[0].shas, vec!["c1", "h1"]);
+        assert_eq!(gitlab[0].reviewers, vec!["bob", "alice"]);
+        assert_eq!(gitlab[0].approvers, vec!["bob", "alice"]);
+        assert_eq!(
+            gitlab[0].merged_at.map(|d| d.to_string()).as_deref(),
+            Some("2024-02-01 12:00:00")
+        );
+        assert_eq!(gitlab[1].merged_at, None);
+
+        let github = parse_export(GITHUB).unwrap();
+        assert_eq!(github[0].mr_id, "#45");
+        // the author's own review comment is not a review
+        assert_eq!(github[0].reviewers, vec!["carol", "peter"]);
+        assert_eq!(github[0].approvers, vec!["peter"]);
+        assert_eq!(github[0].comments, 3);
+        assert_eq!(github[0].labels, vec!["docs"]);
+    }
+
+    #[test]
+    fn test_review_load() {
+        let df = detail_df();
+        let opts = filter_options(false);
+        let metadata = MrMetadata::new(&[(
+            "api".to_string(),
+            [parse_export(GITLAB).unwrap(), parse_export(GITHUB).unwrap()].concat(),
+        )]);
+        let report = MyDataFrame::new(&df, &opts).review_load(&metadata);
+
+        let names = report.column("name").unwrap().str().unwrap();
+        let row = |name: &str| names.into_iter().position(|n| n == Some(name)).unwrap();
+        let get = |c: &str, name: &str| {
+            report
+                .column(c)
+                .unwrap()
+                .cast(&DataType::Int64)
+                .unwrap()
+                .i64()
+                .unwrap()
+                .get(row(name))
+        };
+        // !13 is not linked to any commit
+        assert_eq!(get("mrs_authored", "bob"), Some(1));
+        assert_eq!(get("reviews", "bob"), Some(1));
+        assert_eq!(get("approvals", "peter"), Some(1));
+        assert_eq!(get("reviews", "peter"), Some(1));
+        assert_eq!(get("comments_received", "peter"), Some(3));
+        assert_eq!(get("lines", "peter"), Some(12));
+        // reviewers without commits are listed too
+        assert_eq!(get("commits", "carol"), Some(0));
+        assert_eq!(get("reviews", "alice"), Some(1));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::MyDataFrame;
+use polars::prelude::*;
+
+impl MyDataFrame<'_> {
+    /// 签名合规统计：分别按repo和author统计签名的commit占比、验证通过的占比
+    ///
+    /// `unverified` 是有签名但没有配置公钥无法验证的commit，老的detail数据没有签名列时返回空
+    pub fn signatures(&self) -> DataFrame {
+        if !self.has_column("signed") {
+            return DataFrame::empty();
+        }
+        let q = self.filtered().unique(
+            Some(vec!["repo".to_string(), "commit_id".to_string()]),
+            UniqueKeepStrategy::First,
+        );
+        let count = |e: Expr| e.cast(DataType::UInt32).sum();
+        let valid = col("signature_valid");
+        let aggs = [
+            len().alias("commits"),
+            count(col("signed")).alias("signed"),
+            count(valid.clone().eq(lit(true)).fill_null(lit(false))).alias("verified"),
+            count(valid.clone().eq(lit(false)).fill_null(lit(false))).alias("invalid"),
+            count(col("signed").and(valid.is_null())).alias("unverified"),
+        ];
+        let share = |name: &str| {
+            (col(name).cast(DataType::Float64) / col("commits").cast(DataType::Float64))
+                .alias(&format!("{}_share", name))
+        };
+        let by = |level: &str, column: &str| {
+            q.clone()
+                .group_by([col(column).alias("name")])
+                .agg(aggs.clone())
+                .with_column(lit(level).alias("level"))
+        };
+        concat(
+            [by("repo", "repo"), by("author", "author")],
+            UnionArgs::default(),
+        )
+        .unwrap()
+        .with_columns([share("signed"), share("verified")])
+        .select([
+            col("level"),
+            col("name"),
+            col("commits"),
+            col("signed"),
+            col("signed_share"),
+            col("verified"),
+            col("verified_share"),
+            col("invalid"),
+            col("unverified"),
+        ])
+        .sort(["level", "name"], SortMultipleOptions::default())
+        .collect()
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{detail_df, filter_options};
+    use crate::MyDataFrame;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_signatures() {
+        let opts = filter_options(false);
+        let df = detail_df();
+        assert_eq!(MyDataFrame::new(&df, &opts).signatures().height(), 0);
+
+        // c3, c1, c2, c4: verified, unverified, invalid, unsigned
+        let df = df
+            .lazy()
+            .with_columns([
+                Series::new("signed", [true, true, true, false]).lit(),
+                Series::new("signature_valid", [Some(true), None, Some(false), None]).lit(),
+            ])
+            .collect()
+            .unwrap();
+        let report = MyDataFrame::new(&df, &opts).signatures();
+        // repos api, api-fork + authors bob, peter
+        assert_eq!(report.height(), 4);
+        let names = report.column("name").unwrap().str().unwrap();
+        let row = |name: &str| names.into_iter().position(|n| n == Some(name)).unwrap();
+        let col_u32 = |c: &str, i: usize| report.column(c).unwrap().u32().unwrap().get(i);
+
+        let peter = row("peter");
+        assert_eq!(col_u32("signed", peter), Some(2));
+        assert_eq!(col_u32("verified", peter), Some(1));
+        assert_eq!(col_u32("unverified", peter), Some(1));
+        let bob = row("bob");
+        assert_eq!(col_u32("invalid", bob), Some(1));
+        let share = report.column("signed_share").unwrap().f64().unwrap();
+        assert_eq!(share.get(bob), Some(0.5));
+    }
+}
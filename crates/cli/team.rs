@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use config::Team;
+use polars::prelude::*;
+
+#[derive(Debug, Clone)]
+struct Membership {
+    author: String,
+    /// 团队路径，如 `platform/infra`
+    path: String,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+}
+
+/// 作者在commit日期所属的团队，配置检查保证同一时间只属于一个团队
+#[derive(Debug, Clone, Default)]
+pub struct TeamResolver {
+    memberships: Vec<Membership>,
+    /// 所有团队路径，父团队在子团队之前
+    paths: Vec<String>,
+}
+
+impl TeamResolver {
+    pub fn new(teams: &[Team]) -> Self {
+        let mut resolver = TeamResolver::default();
+        for (path, team) in Team::flatten(teams) {
+            for member in &team.members {
+                let (since, until) = member.dates();
+                resolver.memberships.push(Membership {
+                    author: member.author().to_string(),
+                    path: path.clone(),
+                    since,
+                    until,
+                });
+            }
+            resolver.paths.push(path);
+        }
+        resolver
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// 没有日期时不检查成员的日期范围
+    pub fn resolve(&self, author: &str, date: Option<NaiveDate>) -> Option<&str> {
+        self.memberships
+            .iter()
+            .find(|m| {
+                m.author == author
+                    && date.is_none_or(|date| {
+                        m.since.is_none_or(|since| since <= date)
+                            && m.until.is_none_or(|until| date <= until)
+                    })
+            })
+            .map(|m| m.path.as_str())
+    }
+
+    /// `author`、`date` 列逐行对应的团队路径，不属于任何团队的为null
+    pub fn resolve_series(&self, authors: &Series, dates: &Series) -> PolarsResult<Series> {
+        let authors = authors.str()?;
+        let dates = dates.cast(&DataType::Date)?;
+        let dates = dates.date()?;
+        let teams: StringChunked = (0..authors.len())
+            .map(|i| {
+                let date = dates
+                    .get(i)
+                    .map(|days| NaiveDate::default() + chrono::Duration::days(days as i64));
+                self.resolve(authors.get(i)?, date)
+            })
+            .collect();
+        Ok(teams.with_name("team").into_series())
+    }
+}
+
+/// 团队路径的第一级是组织
+pub fn org(team: Expr) -> Expr {
+    team.str().split(lit("/")).list().first()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let config = config::Config::parse(
+            r#"authors: [{ name: peter }, { name: bob }]
+teams:
+  - name: platform
+    members: [peter]
+    teams:
+      - name: infra
+        members: [{ author: bob, until: 2024-03-31 }]
+  - name: product
+    members: [{ author: bob, since: 2024-04-01 }]
+"#,
+            std::path::Path::new("x.yml"),
+        )
+        .unwrap();
+        let resolver = TeamResolver::new(&config.teams);
+        let d = |s: &str| s.parse::<NaiveDate>().ok();
+        assert_eq!(resolver.paths(), ["platform", "platform/infra", "product"]);
+        assert_eq!(resolver.resolve("peter", d("2024-01-01")), Some("platform"));
+        assert_eq!(
+            resolver.resolve("bob", d("2024-03-31")),
+            Some("platform/infra")
+        );
+        assert_eq!(resolver.resolve("bob", d("2024-04-01")), Some("product"));
+        assert_eq!(resolver.resolve("alice", None), None);
+    }
+}
@@ -0,0 +1,204 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use polars::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// 报表使用的时区，`--tz` 或者配置 `timezone` 指定
+///
+/// 支持 `local`、`utc` 以及固定偏移 `+08:00`/`+0800`/`-05`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportTz {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl FromStr for ReportTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(config::parse_timezone(s)?.map_or(ReportTz::Local, ReportTz::Fixed))
+    }
+}
+
+impl fmt::Display for ReportTz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportTz::Local => write!(f, "local"),
+            ReportTz::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl ReportTz {
+    /// 报表时区的本地时间转成UTC，夏令时重叠时取较早的时间
+    pub fn to_utc(self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            ReportTz::Local => Local
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|d| d.with_timezone(&Utc)),
+            ReportTz::Fixed(offset) => offset
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|d| d.with_timezone(&Utc)),
+        }
+    }
+
+    /// UTC时间转成报表时区的本地时间
+    pub fn local_time(self, utc: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            ReportTz::Local => utc.with_timezone(&Local).naive_local(),
+            ReportTz::Fixed(offset) => utc.with_timezone(&offset).naive_local(),
+        }
+    }
+
+    /// UTC的时间列转成报表时区的本地时间，本地时区按每个时间点各自的偏移(夏令时)
+    pub fn local_expr(self, utc: Expr) -> Expr {
+        match self {
+            ReportTz::Fixed(offset) => {
+                let seconds = lit(offset.local_minus_utc() as i64);
+                utc + duration(DurationArgs::new().with_seconds(seconds))
+            }
+            ReportTz::Local => utc.map(
+                move |s| {
+                    let local: Vec<Option<NaiveDateTime>> = s
+                        .datetime()?
+                        .as_datetime_iter()
+                        .map(|d| d.map(|d| self.local_time(d.and_utc())))
+                        .collect();
+                    Ok(Some(Series::new(s.name(), local).cast(s.dtype())?))
+                },
+                GetOutput::same_type(),
+            ),
+        }
+    }
+}
+
+/// `--since`/`--until` 的输入，带偏移的时间直接确定，其他的按报表时区解释
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateArg {
+    Naive(NaiveDateTime),
+    Offset(DateTime<FixedOffset>),
+}
+
+impl DateArg {
+    /// 解析 `2024-01-01`、`2024-01-01 10:00:00`、`2024-01-01T10:00:00+08:00`
+    ///
+    /// 只有日期时用 `hms` 补全时间
+    pub fn parse(s: &str, hms: [u32; 3]) -> Result<DateArg, String> {
+        let s = s.trim();
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Ok(DateArg::Offset(dt));
+        }
+        for fmt in ["%Y-%m-%d %H:%M:%S%:z", "%Y-%m-%d %H:%M:%S%z"] {
+            if let Ok(dt) = DateTime::parse_from_str(s, fmt) {
+                return Ok(DateArg::Offset(dt));
+            }
+        }
+        for fmt in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+                return Ok(DateArg::Naive(dt));
+            }
+        }
+        match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            Ok(d) => Ok(DateArg::Naive(
+                d.and_hms_opt(hms[0], hms[1], hms[2]).unwrap(),
+            )),
+            Err(e) => Err(format!("invalid date {}: {}", s, e)),
+        }
+    }
+
+    pub fn resolve(&self, tz: &ReportTz) -> Option<DateTime<Utc>> {
+        match self {
+            DateArg::Naive(dt) => tz.to_utc(*dt),
+            DateArg::Offset(dt) => Some(dt.with_timezone(&Utc)),
+        }
+    }
+}
+
+impl fmt::Display for DateArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateArg::Naive(dt) => write!(f, "{}", dt),
+            DateArg::Offset(dt) => write!(f, "{}", dt.to_rfc3339()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tz() {
+        assert_eq!("local".parse::<ReportTz>(), Ok(ReportTz::Local));
+        assert_eq!(
+            "UTC".parse::<ReportTz>(),
+            Ok(ReportTz::Fixed(FixedOffset::east_opt(0).unwrap()))
+        );
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!("+08:00".parse::<ReportTz>(), Ok(ReportTz::Fixed(east8)));
+        assert_eq!("+0800".parse::<ReportTz>(), Ok(ReportTz::Fixed(east8)));
+        assert_eq!("+8".parse::<ReportTz>(), Ok(ReportTz::Fixed(east8)));
+        let west530 = FixedOffset::west_opt(5 * 3600 + 1800).unwrap();
+        assert_eq!("-05:30".parse::<ReportTz>(), Ok(ReportTz::Fixed(west530)));
+        assert!("Asia/Shanghai".parse::<ReportTz>().is_err());
+        assert!("+08:75".parse::<ReportTz>().is_err());
+    }
+
+    #[test]
+    fn test_date_arg_resolve() {
+        let tz: ReportTz = "+08:00".parse().unwrap();
+        let since = DateArg::parse("2024-01-01", [0, 0, 0]).unwrap();
+        assert_eq!(
+            since.resolve(&tz).unwrap().to_rfc3339(),
+            "2023-12-31T16:00:00+00:00"
+        );
+        let until = DateArg::parse("2024-03-31", [23, 59, 59]).unwrap();
+        assert_eq!(
+            until.resolve(&tz).unwrap().to_rfc3339(),
+            "2024-03-31T15:59:59+00:00"
+        );
+        // explicit offset wins over the report timezone
+        let dt = DateArg::parse("2024-01-01T10:00:00-02:00", [0, 0, 0]).unwrap();
+        assert_eq!(
+            dt.resolve(&tz).unwrap().to_rfc3339(),
+            "2024-01-01T12:00:00+00:00"
+        );
+        let dt = DateArg::parse("2024-01-01 10:00:00", [0, 0, 0]).unwrap();
+        assert_eq!(
+            dt.resolve(&tz).unwrap().to_rfc3339(),
+            "2024-01-01T02:00:00+00:00"
+        );
+        assert!(DateArg::parse("2024/01/01", [0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_local_expr() {
+        let utc = NaiveDate::from_ymd_opt(2024, 2, 29)
+            .unwrap()
+            .and_hms_opt(20, 0, 0)
+            .unwrap();
+        let df = df!("date" => [utc]).unwrap();
+        let local = |tz: ReportTz| {
+            let df = df
+                .clone()
+                .lazy()
+                .select([tz.local_expr(col("date"))])
+                .collect()
+                .unwrap();
+            let date = df.column("date").unwrap().datetime().unwrap().clone();
+            let local = date.as_datetime_iter().next().unwrap().unwrap();
+            local
+        };
+        assert_eq!(
+            local("+08:00".parse().unwrap()).to_string(),
+            "2024-03-01 04:00:00"
+        );
+        assert_eq!(
+            local(ReportTz::Local),
+            ReportTz::Local.local_time(utc.and_utc())
+        );
+    }
+}
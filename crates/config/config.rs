@@ -1,60 +1,575 @@
+use crate::load::resolve;
+use crate::secret::Secret;
+use crate::validate::ConfigError;
+use chrono::NaiveDate;
 use serde::Deserialize;
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::path::Path;
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// 先加载的配置文件，路径相对于当前文件，当前文件的设置覆盖它们，`authors`/`repos` 追加
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
     pub authors: Vec<Author>,
+    /// 机器人账号，匹配规则同作者alias，优先于作者匹配，commit单独归到bots
+    #[serde(default)]
+    pub bots: Vec<Alias>,
+    #[serde(default)]
     pub repos: Vec<Repo>,
+    /// 团队层级，顶层团队即组织(org)
+    #[serde(default)]
+    pub teams: Vec<Team>,
+    /// 报表和日期过滤使用的时区: local, utc, +08:00，`--tz` 优先
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub business_hours: BusinessHours,
+    #[serde(default)]
+    pub hotspot: HotspotWeights,
+    #[serde(default)]
+    pub coupling: CouplingOptions,
+    #[serde(default)]
+    pub outliers: OutlierOptions,
+    #[serde(default)]
+    pub bus_factor: BusFactorOptions,
+    #[serde(default)]
+    pub signatures: SignatureOptions,
+    #[serde(default)]
+    pub message_lint: MessageLintOptions,
+    /// loc-history的采样间隔: month, week, 14d，`--interval` 优先
+    pub loc_interval: Option<String>,
+    /// 命名的配置层，`--profile` 选中后合并到上面的配置之上
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Config>,
+}
+
+/// 超大commit的阈值，满足任意一个即标记为outlier
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct OutlierOptions {
+    /// insertions + deletions 超过这个行数
+    pub max_lines: Option<u64>,
+    /// 超过所在repo commit大小的分位数，如 0.99
+    pub percentile: Option<f64>,
+}
+
+impl Default for OutlierOptions {
+    fn default() -> Self {
+        OutlierOptions {
+            max_lines: Some(5000),
+            percentile: None,
+        }
+    }
+}
+
+/// commit message检查规则，设置为空(null/false/[])的规则不检查
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MessageLintOptions {
+    /// 第一行(subject)的最大字符数
+    pub max_subject_length: Option<usize>,
+    /// subject是否使用祈使句，启发式: 第一个词不能是 added/adding/adds/built 这种形式，embed/string/alias等常见词除外
+    pub imperative: bool,
+    /// insertions + deletions 达到这个行数的commit必须有body
+    pub body_required_lines: Option<u64>,
+    /// subject开头不能出现的标记，不区分大小写
+    pub wip_markers: Vec<String>,
+    /// message中必须包含的issue编号，正则，如 `[A-Z][A-Z0-9]+-\d+`
+    pub issue_pattern: Option<String>,
+}
+
+impl Default for MessageLintOptions {
+    fn default() -> Self {
+        MessageLintOptions {
+            max_subject_length: Some(72),
+            imperative: true,
+            body_required_lines: Some(300),
+            wip_markers: ["wip", "fixup!", "squash!", "amend!", "tmp"]
+                .map(String::from)
+                .to_vec(),
+            issue_pattern: None,
+        }
+    }
+}
+
+/// 验证commit签名使用的本地文件，没有配置的签名类型只统计是否签名
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct SignatureOptions {
+    /// ssh签名的allowed signers文件，格式同git的`gpg.ssh.allowedSignersFile`
+    pub allowed_signers: Option<String>,
+    /// gpg签名使用的公钥keyring文件(`gpg --export > keyring.gpg`)
+    pub gpg_keyring: Option<String>,
+}
+
+/// 知识集中度(bus factor)报表的参数
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct BusFactorOptions {
+    /// 改动占比累计达到这个比例所需的最少作者数即为bus factor
+    pub threshold: f64,
+    /// 主要owner超过这么多天没有commit则标记为风险
+    pub inactive_days: i64,
+    /// 目录取前几层，默认只看顶层目录
+    pub dir_depth: usize,
+}
+
+impl Default for BusFactorOptions {
+    fn default() -> Self {
+        BusFactorOptions {
+            threshold: 0.5,
+            inactive_days: 90,
+            dir_depth: 1,
+        }
+    }
+}
+
+/// 变更耦合分析的阈值
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CouplingOptions {
+    /// 最少一起变更的commit数
+    pub min_co_changes: u32,
+    /// 两者各自最少的变更commit数
+    pub min_commits: u32,
+    /// 最小耦合度 0~1
+    pub min_degree: f64,
+    /// 超过这么多文件的commit不参与分析
+    pub max_files: usize,
+    /// 目录对只取前几层目录，不设置则为文件的完整父目录
+    pub dir_depth: Option<usize>,
+}
+
+impl Default for CouplingOptions {
+    fn default() -> Self {
+        CouplingOptions {
+            min_co_changes: 3,
+            min_commits: 3,
+            min_degree: 0.3,
+            max_files: 30,
+            dir_depth: None,
+        }
+    }
+}
+
+/// hotspot分数中各项指标的权重，各指标先按最大值归一化到0~1
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct HotspotWeights {
+    pub commits: f64,
+    pub authors: f64,
+    pub churn: f64,
+    pub recency: f64,
+}
+
+impl Default for HotspotWeights {
+    fn default() -> Self {
+        HotspotWeights {
+            commits: 1.0,
+            authors: 1.0,
+            churn: 1.0,
+            recency: 1.0,
+        }
+    }
+}
+
+/// 工作时间，按commit作者自己的时区判断
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BusinessHours {
+    /// 开始小时，包含
+    pub start: u32,
+    /// 结束小时，不包含
+    pub end: u32,
+    /// 工作日，ISO编号 1=周一 ... 7=周日
+    pub workdays: Vec<u32>,
+}
+
+impl Default for BusinessHours {
+    fn default() -> Self {
+        BusinessHours {
+            start: 9,
+            end: 18,
+            workdays: vec![1, 2, 3, 4, 5],
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Repo {
     pub url: String,
+    /// 报表中的repo名和 `./repos` 下的clone路径，默认由url的host和路径生成，见 `repo_name`
+    pub name: Option<String>,
     username: Option<String>,
-    password: Option<String>,
+    password: Option<Secret>,
+    /// 从文件读取密码(去掉末尾换行)，相对路径基于配置文件所在目录
+    password_file: Option<String>,
+    /// 执行命令取密码，如 `pass show gitlab/token`
+    password_cmd: Option<String>,
     pub branches: Vec<String>,
     pub pathspec: Vec<String>,
+    /// 文件分类规则，优先于内置规则，见 `FileCategory`
+    #[serde(default)]
+    pub categories: Vec<CategoryRule>,
+    /// gitlab/github导出的MR JSON，用于review统计
+    pub mr_export: Option<String>,
+    /// 读取git历史的方式，默认libgit2
+    #[serde(default)]
+    pub backend: GitBackend,
+    /// 只统计这个日期(UTC，包含)之后的commit，如导入的旧历史
+    pub since: Option<NaiveDate>,
+    /// 只统计这个日期(UTC，包含)之前的commit
+    pub until: Option<NaiveDate>,
+    /// 不统计的作者，配置的作者名或git中的名字
+    #[serde(default)]
+    pub exclude_authors: Vec<String>,
+    /// 不统计的email，正则，不区分大小写
+    #[serde(default)]
+    pub exclude_emails: Vec<String>,
+    /// 不统计的commit message，正则
+    #[serde(default)]
+    pub ignore_messages: Vec<String>,
+}
+
+/// git backend，`cli` 调用系统的git，使用系统的ssh配置、credential helper、Kerberos等
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBackend {
+    #[default]
+    Libgit2,
+    Cli,
+}
+
+/// 变更文件的分类
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+    Test,
+    Production,
+    Docs,
+    Config,
+}
+
+impl FileCategory {
+    pub const ALL: [FileCategory; 4] = [
+        FileCategory::Test,
+        FileCategory::Production,
+        FileCategory::Docs,
+        FileCategory::Config,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileCategory::Test => "test",
+            FileCategory::Production => "production",
+            FileCategory::Docs => "docs",
+            FileCategory::Config => "config",
+        }
+    }
+}
+
+/// 匹配 `patterns`（pathspec fnmatch语法）的文件归为 `category`
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryRule {
+    pub category: FileCategory,
+    pub patterns: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Author {
     pub name: String,
-    pub alias: Vec<String>,
+    /// 匹配这个作者的规则，作者名本身也精确匹配
+    #[serde(default)]
+    pub alias: Vec<Alias>,
+}
+
+/// 作者身份的匹配规则，字符串是精确的作者名
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Alias {
+    Name(String),
+    Rule(AliasRule),
+}
+
+/// `name`/`email`/`email_domain`/`regex` 设置其中一个，`since`/`until` 限定commit日期(包含)，用于改名、账号交接
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AliasRule {
+    /// 精确的作者名
+    pub name: Option<String>,
+    /// email，不区分大小写
+    pub email: Option<String>,
+    /// email的域名，如 `corp.com`，不区分大小写，不包括子域名
+    pub email_domain: Option<String>,
+    /// 作者名的正则，需要完整匹配时写 `^...$`
+    pub regex: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+/// alias规则中的匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchKind {
+    Name,
+    Email,
+    EmailDomain,
+    Regex,
+}
+
+impl MatchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchKind::Name => "name",
+            MatchKind::Email => "email",
+            MatchKind::EmailDomain => "email_domain",
+            MatchKind::Regex => "regex",
+        }
+    }
+}
+
+impl Alias {
+    /// 匹配方式和值，规则中没有或者设置了多个匹配方式时返回错误
+    pub fn matcher(&self) -> Result<(MatchKind, &str), String> {
+        let rule = match self {
+            Alias::Name(name) => return Ok((MatchKind::Name, name)),
+            Alias::Rule(rule) => rule,
+        };
+        let set: Vec<(MatchKind, &str)> = [
+            (MatchKind::Name, &rule.name),
+            (MatchKind::Email, &rule.email),
+            (MatchKind::EmailDomain, &rule.email_domain),
+            (MatchKind::Regex, &rule.regex),
+        ]
+        .into_iter()
+        .filter_map(|(kind, value)| value.as_deref().map(|v| (kind, v)))
+        .collect();
+        match set.as_slice() {
+            [one] => Ok(*one),
+            [] => Err("alias needs one of name, email, email_domain, regex".to_string()),
+            _ => Err(format!(
+                "alias can only have one of {}",
+                set.iter()
+                    .map(|(k, _)| k.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    /// 生效的日期范围(包含)
+    pub fn dates(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        match self {
+            Alias::Name(_) => (None, None),
+            Alias::Rule(rule) => (rule.since, rule.until),
+        }
+    }
+}
+
+/// 团队，`teams` 嵌套下级团队，成员是 `authors` 中的作者名
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Team {
+    pub name: String,
+    #[serde(default)]
+    pub members: Vec<Member>,
+    #[serde(default)]
+    pub teams: Vec<Team>,
+}
+
+/// 团队成员，字符串是一直在这个团队的作者名
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Member {
+    Author(String),
+    Period(Membership),
+}
+
+/// 一段时间内的成员，`since`/`until` 限定commit日期(包含)，用于换团队的人
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Membership {
+    pub author: String,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl Member {
+    pub fn author(&self) -> &str {
+        match self {
+            Member::Author(author) => author,
+            Member::Period(membership) => &membership.author,
+        }
+    }
+
+    /// 生效的日期范围(包含)
+    pub fn dates(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        match self {
+            Member::Author(_) => (None, None),
+            Member::Period(membership) => (membership.since, membership.until),
+        }
+    }
+}
+
+impl Team {
+    /// 所有团队及其路径，如 `platform/infra`，父团队在子团队之前
+    pub fn flatten(teams: &[Team]) -> Vec<(String, &Team)> {
+        let mut out = vec![];
+        for team in teams {
+            out.push((team.name.clone(), team));
+            for (path, sub) in Team::flatten(&team.teams) {
+                out.push((format!("{}/{}", team.name, path), sub));
+            }
+        }
+        out
+    }
 }
 
 impl Config {
-    pub fn new(filename: &str) -> Config {
-        let reader = File::open(filename).unwrap();
-        let config: Config = serde_yaml::from_reader(reader).unwrap();
-        config
+    /// 读取配置文件，展开include，选中 `profile`，有问题时返回所有问题的位置
+    pub fn load(path: impl AsRef<Path>, profile: Option<&str>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            file: path.to_path_buf(),
+            source,
+        })?;
+        resolve(&text, path, profile)
+    }
+
+    /// 解析并检查配置内容，include相对于 `file` 所在目录
+    pub fn parse(text: &str, file: &Path) -> Result<Config, ConfigError> {
+        resolve(text, file, None)
     }
 }
 
 // 为Struct实现一个方法
 impl Repo {
-    pub fn repo_name(&self) -> &str {
-        self.url
-            .split("/")
-            .last()
-            .unwrap()
-            .split(".")
-            .nth(0)
-            .unwrap()
+    /// 没有认证、pathspec和分类规则的repo
+    pub fn new(url: &str, branches: Vec<String>) -> Self {
+        Repo {
+            url: url.to_string(),
+            name: None,
+            username: None,
+            password: None,
+            password_file: None,
+            password_cmd: None,
+            branches,
+            pathspec: vec![],
+            categories: vec![],
+            mr_export: None,
+            backend: GitBackend::default(),
+            since: None,
+            until: None,
+            exclude_authors: vec![],
+            exclude_emails: vec![],
+            ignore_messages: vec![],
+        }
     }
 
-    pub fn username(&self) -> &str {
-        if self.username.is_some() {
-            return self.username.as_ref().unwrap();
-        } else {
-            return "";
+    /// 配置的 `name`，没有时是url的host加路径，如 `github.com/group-a/api`
+    pub fn repo_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => Repo::default_name(&self.url),
         }
     }
-    pub fn password(&self) -> &str {
-        if self.password.is_some() {
-            return self.password.as_ref().unwrap();
+
+    /// 去掉scheme、用户名密码、端口和 `.git` 后缀，本地路径去掉开头的 `/` 和 `..`
+    pub fn default_name(url: &str) -> String {
+        let (host, path) = if let Some((_, rest)) = url.split_once("://") {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            (authority, path)
         } else {
-            return "";
+            match url.split_once(':') {
+                // scp风格 `user@host:path`，排除windows盘符 `C:\`
+                Some((authority, path))
+                    if authority.len() > 1 && !authority.contains(['/', '\\']) =>
+                {
+                    (authority, path)
+                }
+                _ => ("", url),
+            }
+        };
+        let host = host.rsplit('@').next().unwrap_or("");
+        let host = host.split(':').next().unwrap_or("");
+        let path = path.trim_end_matches(['/', '\\']);
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        std::iter::once(host)
+            .chain(path.split(['/', '\\']))
+            .filter(|s| !s.is_empty() && *s != "." && *s != ".." && !s.ends_with(':'))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// 旧版本的repo名(url最后一段第一个 `.` 之前)，用于迁移旧的clone目录
+    pub fn legacy_name(&self) -> &str {
+        let last = self.url.trim_end_matches('/').rsplit(['/', ':']).next();
+        last.unwrap_or("").split('.').next().unwrap_or("")
+    }
+
+    pub fn username(&self) -> &str {
+        self.username.as_deref().unwrap_or("")
+    }
+    pub fn password(&self) -> &str {
+        self.password.as_ref().map(Secret::expose).unwrap_or("")
+    }
+
+    /// 设置了几种密码来源，最多只能有一个
+    pub(crate) fn password_sources(&self) -> Vec<&'static str> {
+        [
+            (self.password.is_some(), "password"),
+            (self.password_file.is_some(), "password_file"),
+            (self.password_cmd.is_some(), "password_cmd"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect()
+    }
+
+    /// 读取 `password_file`(加载时已经相对于所在文件)/`password_cmd`(在 `dir` 中执行)，失败时返回出问题的配置值和原因
+    pub(crate) fn resolve_password(&mut self, dir: &Path) -> Result<(), (String, String)> {
+        if let Some(file) = &self.password_file {
+            let text = std::fs::read_to_string(file)
+                .map_err(|e| (file.clone(), format!("read password_file {}: {}", file, e)))?;
+            self.password = Some(Secret::new(text.trim_end_matches(['\n', '\r'])));
+        }
+        if let Some(cmd) = &self.password_cmd {
+            let mut command = if cfg!(windows) {
+                let mut c = std::process::Command::new("cmd");
+                c.arg("/C");
+                c
+            } else {
+                let mut c = std::process::Command::new("sh");
+                c.arg("-c");
+                c
+            };
+            let output = command
+                .arg(cmd)
+                .current_dir(dir)
+                .stderr(std::process::Stdio::inherit())
+                .output()
+                .map_err(|e| (cmd.clone(), format!("run password_cmd: {}", e)))?;
+            if !output.status.success() {
+                return Err((
+                    cmd.clone(),
+                    format!("password_cmd exited with {}", output.status),
+                ));
+            }
+            let text = String::from_utf8_lossy(&output.stdout);
+            self.password = Some(Secret::new(text.trim_end_matches(['\n', '\r'])));
         }
+        Ok(())
     }
 }
 
@@ -84,9 +599,330 @@ repos:
         );
         assert_eq!(config.repos[0].branches[0], "main");
         assert_eq!(config.authors[0].name, "26huitailang");
-        assert_eq!(config.authors[0].alias, &["peterChen"]);
+        assert_eq!(
+            config.authors[0].alias,
+            &[Alias::Name("peterChen".to_string())]
+        );
         assert_eq!(config.repos[0].pathspec[0], "*.go");
         assert_eq!(config.repos[0].pathspec[1], "!framework");
         assert_eq!(config.repos[0].pathspec[2], "!vendor");
+        assert_eq!(config.business_hours, BusinessHours::default());
+        assert!(config.repos[0].categories.is_empty());
+    }
+
+    #[test]
+    fn test_repo_name() {
+        let cases = [
+            (
+                "https://github.com/group-a/api.git",
+                "github.com/group-a/api",
+            ),
+            (
+                "https://oauth2:pw@gitlab.com:8443/g/sub/my.service.git",
+                "gitlab.com/g/sub/my.service",
+            ),
+            ("git@github.com:group-b/api.git", "github.com/group-b/api"),
+            ("ssh://git@host/api/", "host/api"),
+            ("file:///srv/git/api.git", "srv/git/api"),
+            ("/tmp/e2e/demo.git", "tmp/e2e/demo"),
+            ("../src/api", "src/api"),
+            ("C:\\src\\api", "src/api"),
+        ];
+        for (url, name) in cases {
+            assert_eq!(Repo::default_name(url), name, "{}", url);
+        }
+        let mut repo = Repo::new("https://gitlab.com/g/my.service.git", vec![]);
+        assert_eq!(repo.legacy_name(), "my");
+        repo.name = Some("service".to_string());
+        assert_eq!(repo.repo_name(), "service");
+        assert_eq!(Repo::new("git@host:api.git", vec![]).legacy_name(), "api");
+    }
+
+    #[test]
+    fn test_categories() {
+        let content = r##"authors: []
+repos:
+  - url: https://github.com/26huitailang/yogo.git
+    branches: [main]
+    pathspec: []
+    categories:
+      - category: test
+        patterns: ["*/testdata/*", "e2e/*"]
+      - category: docs
+        patterns: ["*.adoc"]
+"##;
+        let config: Config = serde_yaml::from_str(content).unwrap();
+        let categories = &config.repos[0].categories;
+        assert_eq!(categories.len(), 2);
+        assert_eq!(categories[0].category, FileCategory::Test);
+        assert_eq!(categories[0].patterns, vec!["*/testdata/*", "e2e/*"]);
+        assert_eq!(categories[1].category.as_str(), "docs");
+    }
+
+    #[test]
+    fn test_business_hours() {
+        let content = r##"authors: []
+repos: []
+business_hours:
+  start: 10
+  end: 19
+  workdays: [1, 2, 3, 4, 5, 6]
+"##;
+        let config: Config = serde_yaml::from_str(content).unwrap();
+        assert_eq!(config.business_hours.start, 10);
+        assert_eq!(config.business_hours.end, 19);
+        assert_eq!(config.business_hours.workdays, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(config.hotspot, HotspotWeights::default());
+        assert_eq!(config.coupling, CouplingOptions::default());
+        assert_eq!(config.outliers.max_lines, Some(5000));
+    }
+
+    #[test]
+    fn test_hotspot_weights() {
+        let content = r##"authors: []
+repos: []
+hotspot:
+  churn: 2.0
+  recency: 0
+"##;
+        let config: Config = serde_yaml::from_str(content).unwrap();
+        assert_eq!(config.hotspot.commits, 1.0);
+        assert_eq!(config.hotspot.churn, 2.0);
+        assert_eq!(config.hotspot.recency, 0.0);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let file = Path::new(".git-stat.yml");
+        let content = r##"authors: []
+repos:
+  - url: https://github.com/26huitailang/yogo.git
+    branchs: [main]
+    pathspec: []
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let diagnostic = &err.diagnostics()[0];
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        assert_eq!((diagnostic.line, diagnostic.column), (Some(4), Some(5)));
+        assert!(diagnostic.message.contains("unknown field `branchs`"));
+
+        let content = r##"authors:
+  - name: peter
+    alias: [peterChen, pc]
+  - name: bob
+    alias: [bob, pc]
+repos:
+  - url: https://github.com/26huitailang/yogo.git
+    branches: [main]
+    pathspec: ["*.go", "src/[ab"]
+  - url: https://gitlab.example.com/mirror/yogo.git
+    name: github.com/26huitailang/yogo
+    branches: [main]
+    pathspec: []
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let messages: Vec<String> = err.to_string().lines().map(|l| l.to_string()).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].starts_with(".git-stat.yml:9:25: repos[0]: unclosed `[`"));
+        assert!(messages[1].starts_with(
+            ".git-stat.yml:10:10: repos[1]: duplicate repo name `github.com/26huitailang/yogo`"
+        ));
+        assert_eq!(
+            messages[2],
+            ".git-stat.yml:5:18: alias `pc` is mapped to both `peter` and `bob`"
+        );
+
+        let err = Config::load("no-such-config.yml", None).unwrap_err();
+        assert!(matches!(err, ConfigError::Io { .. }));
+    }
+
+    #[test]
+    fn test_alias_rules() {
+        let file = Path::new(".git-stat.yml");
+        let content = r##"authors:
+  - name: peter
+    alias:
+      - peterChen
+      - { email: Peter@Corp.com }
+      - { regex: "^Peter( Chen)?$" }
+      - { name: pc, until: 2023-06-30 }
+  - name: bob
+    alias:
+      - { name: pc, since: 2023-07-01 }
+      - { email_domain: vendor.com }
+"##;
+        let config = Config::parse(content, file).unwrap();
+        let alias = &config.authors[0].alias;
+        assert_eq!(alias[1].matcher(), Ok((MatchKind::Email, "Peter@Corp.com")));
+        assert_eq!(alias[2].matcher().unwrap().0, MatchKind::Regex);
+        assert_eq!(
+            config.authors[1].alias[0].dates(),
+            ("2023-07-01".parse().ok(), None)
+        );
+
+        let content = r##"authors:
+  - name: peter
+    alias:
+      - { name: pc, until: 2023-06-30 }
+      - { email: peter@corp.com, name: peter }
+      - { regex: "(unclosed" }
+  - name: bob
+    alias:
+      - { name: pc, since: 2023-06-30 }
+      - { email_domain: "@vendor.com" }
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let messages: Vec<String> = err.to_string().lines().map(|l| l.to_string()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages[0].contains("alias can only have one of name, email"));
+        assert!(messages[1]
+            .starts_with(".git-stat.yml:6:19: invalid regex `(unclosed`: unclosed group"));
+        assert_eq!(
+            messages[2],
+            ".git-stat.yml:9:17: alias `pc` is mapped to both `peter` and `bob`"
+        );
+        assert!(messages[3].contains("email_domain `@vendor.com`"));
+    }
+
+    #[test]
+    fn test_teams() {
+        let file = Path::new(".git-stat.yml");
+        let content = r##"authors:
+  - name: peter
+  - name: bob
+teams:
+  - name: platform
+    members: [peter]
+    teams:
+      - name: infra
+        members:
+          - { author: bob, until: 2024-03-31 }
+  - name: product
+    members:
+      - { author: bob, since: 2024-04-01 }
+"##;
+        let config = Config::parse(content, file).unwrap();
+        let paths: Vec<String> = Team::flatten(&config.teams)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, ["platform", "platform/infra", "product"]);
+        assert_eq!(config.teams[1].members[0].author(), "bob");
+
+        let content = r##"authors:
+  - name: peter
+teams:
+  - name: platform
+    members: [peter, alice]
+  - name: infra
+    members:
+      - { author: peter, since: 2024-01-01 }
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let messages: Vec<String> = err.to_string().lines().map(|l| l.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                ".git-stat.yml:4:5: team `platform`: unknown author `alice`, members should be names in authors",
+                ".git-stat.yml:6:5: `peter` is a member of both `platform` and `infra` at the same time",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repo_filters_and_bots() {
+        let file = Path::new(".git-stat.yml");
+        let content = r##"bots:
+  - "dependabot[bot]"
+  - { regex: "\\[bot\\]$" }
+repos:
+  - url: https://github.com/group/api.git
+    branches: [main]
+    pathspec: []
+    since: 2020-01-01
+    exclude_authors: [svn-import]
+    exclude_emails: ["@ci\\.corp\\.com$"]
+    ignore_messages: ["^Imported from SVN"]
+"##;
+        let config = Config::parse(content, file).unwrap();
+        assert_eq!(config.bots.len(), 2);
+        let repo = &config.repos[0];
+        assert_eq!(repo.since, "2020-01-01".parse().ok());
+        assert_eq!(repo.exclude_authors, ["svn-import"]);
+
+        let content = r##"bots:
+  - { email: ci.corp.com }
+repos:
+  - url: https://github.com/group/api.git
+    branches: [main]
+    pathspec: []
+    since: 2021-01-01
+    until: 2020-01-01
+    ignore_messages: ["(unclosed"]
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let messages: Vec<String> = err.to_string().lines().map(|l| l.to_string()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("repos[0]: since 2021-01-01 is after until 2020-01-01"));
+        assert!(messages[1].starts_with(".git-stat.yml:9:24: repos[0]: invalid regex `(unclosed`"));
+        assert_eq!(
+            messages[2],
+            ".git-stat.yml:2:14: bots: invalid email `ci.corp.com`"
+        );
+    }
+
+    #[test]
+    fn test_report_options() {
+        let file = Path::new(".git-stat.yml");
+        let content = r##"timezone: "+0800"
+loc_interval: 14d
+message_lint:
+  issue_pattern: "[A-Z]+-\\d+"
+repos: []
+"##;
+        let config = Config::parse(content, file).unwrap();
+        assert_eq!(config.loc_interval.as_deref(), Some("14d"));
+
+        let content = r##"timezone: Asia/Shanghai
+loc_interval: quarter
+outliers:
+  percentile: 99
+message_lint:
+  issue_pattern: "(JIRA-"
+repos: []
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let messages: Vec<String> = err.to_string().lines().map(|l| l.to_string()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages[0].starts_with(".git-stat.yml:1:11: timezone: invalid timezone"));
+        assert!(messages[1].starts_with(".git-stat.yml:2:15: loc_interval: invalid interval"));
+        assert_eq!(
+            messages[2],
+            ".git-stat.yml:4:3: outliers.percentile: 99 should be in (0, 1], e.g. 0.99"
+        );
+        assert!(messages[3]
+            .starts_with(".git-stat.yml:6:19: message_lint.issue_pattern: invalid regex `(JIRA-`"));
+
+        let content = r##"business_hours:
+  start: 18
+  end: 9
+  workdays: [1, 2, 8]
+coupling:
+  min_degree: 1.5
+bus_factor:
+  threshold: 0
+repos: []
+"##;
+        let err = Config::parse(content, file).unwrap_err();
+        let messages: Vec<String> = err.to_string().lines().map(|l| l.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                ".git-stat.yml:1:1: business_hours: start 18 should be before end 9, and end at most 24",
+                ".git-stat.yml:4:3: business_hours.workdays: 8 should be 1 (Monday) to 7 (Sunday)",
+                ".git-stat.yml:6:3: coupling.min_degree: 1.5 should be in [0, 1]",
+                ".git-stat.yml:8:3: bus_factor.threshold: 0 should be in (0, 1]",
+            ]
+        );
     }
 }
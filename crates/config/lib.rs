@@ -1,2 +1,10 @@
 pub mod config;
+pub mod load;
+pub mod secret;
+pub mod time;
+pub mod validate;
 pub use config::*;
+pub use load::{discover, CONFIG_FILE};
+pub use secret::{redact, Secret};
+pub use time::{parse_timezone, Interval};
+pub use validate::{ConfigError, Diagnostic};
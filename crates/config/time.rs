@@ -0,0 +1,62 @@
+//! 配置和命令行共用的时区、采样间隔格式，加载配置时检查，报表时使用
+use chrono::{FixedOffset, Offset, Utc};
+use std::str::FromStr;
+
+/// `local` 返回None，`utc`/`z` 以及固定偏移 `+08:00`/`+0800`/`-05` 返回偏移
+pub fn parse_timezone(s: &str) -> Result<Option<FixedOffset>, String> {
+    let s = s.trim();
+    match s.to_lowercase().as_str() {
+        "local" => return Ok(None),
+        "utc" | "z" => return Ok(Some(Utc.fix())),
+        _ => {}
+    }
+    parse_offset(s)
+        .map(Some)
+        .ok_or_else(|| format!("invalid timezone: {}, expect local|utc|+08:00", s))
+}
+
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse().ok()?, digits[2..].parse().ok()?),
+        _ => return None,
+    };
+    if m >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+}
+
+/// loc-history的采样间隔
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Week,
+    Month,
+    Days(u32),
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "week" => Ok(Interval::Week),
+            "month" => Ok(Interval::Month),
+            s => s
+                .strip_suffix('d')
+                .and_then(|n| n.parse::<u32>().ok())
+                .filter(|n| *n > 0)
+                .map(Interval::Days)
+                .ok_or_else(|| format!("invalid interval: {}, expect month|week|14d", s)),
+        }
+    }
+}
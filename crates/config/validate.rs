@@ -0,0 +1,746 @@
+use crate::config::{Config, MatchKind, Team};
+use crate::load::Source;
+use crate::time::{parse_timezone, Interval};
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// 一条配置问题，位置是1开始的行列，找不到时为None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 问题所在的文件，可能是include的文件
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// 加载配置的错误
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 文件不存在或无法读取
+    Io {
+        file: PathBuf,
+        source: std::io::Error,
+    },
+    /// yaml语法错误、类型不对、未知字段
+    Parse {
+        file: PathBuf,
+        diagnostic: Diagnostic,
+    },
+    /// 语义检查没有通过，列出所有问题
+    Invalid {
+        file: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+impl ConfigError {
+    /// 所有问题，Io错误没有位置
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            ConfigError::Io { file, source } => vec![Diagnostic {
+                file: file.clone(),
+                line: None,
+                column: None,
+                message: source.to_string(),
+            }],
+            ConfigError::Parse { diagnostic, .. } => vec![diagnostic.clone()],
+            ConfigError::Invalid { diagnostics, .. } => diagnostics.clone(),
+        }
+    }
+
+    /// 加载的配置文件
+    pub fn file(&self) -> &Path {
+        match self {
+            ConfigError::Io { file, .. }
+            | ConfigError::Parse { file, .. }
+            | ConfigError::Invalid { file, .. } => file,
+        }
+    }
+}
+
+/// `file:line:column: message`，一行一个问题
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .diagnostics()
+            .iter()
+            .map(|d| (d.file.display(), d))
+            .map(|(file, d)| match (d.line, d.column) {
+                (Some(line), Some(column)) => {
+                    format!("{}:{}:{}: {}", file, line, column, d.message)
+                }
+                (Some(line), None) => format!("{}:{}: {}", file, line, d.message),
+                _ => format!("{}: {}", file, d.message),
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// serde_yaml的错误信息已经带了位置，去掉重复的 ` at line x column y`
+pub(crate) fn parse_diagnostic(e: &serde_yaml::Error, file: &Path) -> Diagnostic {
+    let location = e.location();
+    let message = e.to_string();
+    let message = match message.find(" at line ") {
+        Some(idx) if location.is_some() => message[..idx].to_string(),
+        _ => message,
+    };
+    Diagnostic {
+        file: file.to_path_buf(),
+        line: location.as_ref().map(|l| l.line()),
+        column: location.as_ref().map(|l| l.column()),
+        message,
+    }
+}
+
+/// 字节位置`idx`的行列，serde_yaml的值没有位置信息，按文本查找
+fn line_column(text: &str, idx: usize) -> (usize, usize) {
+    let before = &text[..idx];
+    let line = before.matches('\n').count() + 1;
+    let column = idx - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// `source` 中 `idx` 处的位置，没有`idx`时只有文件
+fn diagnostic_at(source: &Source, idx: Option<usize>) -> Diagnostic {
+    let position = idx.map(|idx| line_column(&source.text, idx));
+    Diagnostic {
+        file: source.file.clone(),
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+        message: String::new(),
+    }
+}
+
+/// 在所有文件中只出现一次的`needle`的位置，出现多次时不知道是哪一个，只报告第一个所在的文件，
+/// 找不到时定位到最后一个(加载的)文件
+pub(crate) fn locate_unique(sources: &[Source], needle: &str) -> Diagnostic {
+    let found: Vec<(&Source, usize)> = sources
+        .iter()
+        .filter(|_| !needle.is_empty())
+        .flat_map(|s| s.text.match_indices(needle).map(move |(idx, _)| (s, idx)))
+        .collect();
+    match found.as_slice() {
+        [(source, idx)] => diagnostic_at(source, Some(*idx)),
+        [(source, _), ..] => diagnostic_at(source, None),
+        [] => Diagnostic {
+            file: sources.last().map(|s| s.file.clone()).unwrap_or_default(),
+            line: None,
+            column: None,
+            message: String::new(),
+        },
+    }
+}
+
+/// 顶层 `section:` 下block风格列表每一项的字节范围，flow风格的 `[...]` 没有
+fn entries(text: &str, section: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut in_section = false;
+    let mut item_indent = None;
+    let mut start = None;
+    let mut end = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let content = line.trim_end();
+        let trimmed = content.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = content.len() - trimmed.len();
+        let is_item = trimmed == "-" || trimmed.starts_with("- ");
+        if indent == 0 && !is_item {
+            ranges.extend(start.take().map(|s| s..end));
+            in_section = trimmed
+                .strip_prefix(section)
+                .is_some_and(|rest| rest.trim_end() == ":");
+            item_indent = None;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if is_item && *item_indent.get_or_insert(indent) == indent {
+            ranges.extend(start.replace(line_start).map(|s| s..end));
+        }
+        end = line_start + content.len();
+    }
+    ranges.extend(start.map(|s| s..end));
+    ranges
+}
+
+/// `text` 中有完整的`word`，前后不是名字、url中的字符
+fn contains_word(text: &str, word: &str) -> bool {
+    let part = |c: char| c.is_alphanumeric() || "_-./@".contains(c);
+    !word.is_empty()
+        && text.match_indices(word).any(|(idx, _)| {
+            !text[..idx].chars().next_back().is_some_and(part)
+                && !text[idx + word.len()..].chars().next().is_some_and(part)
+        })
+}
+
+/// `section` 列表中包含`anchor`(repo的url、作者名)的第nth项里第一次出现`needle`的位置，
+/// 相同的值出现在别的项中时不会定位错；项里没有`needle`时定位到项的第一行，找不到这一项时按 `locate_unique`
+pub(crate) fn locate_entry(
+    sources: &[Source],
+    section: &str,
+    anchor: &str,
+    nth: usize,
+    needle: &str,
+) -> Diagnostic {
+    let entry = sources
+        .iter()
+        .flat_map(|s| entries(&s.text, section).into_iter().map(move |r| (s, r)))
+        .filter(|(s, r)| contains_word(&s.text[r.clone()], anchor))
+        .nth(nth);
+    let Some((source, range)) = entry else {
+        return locate_unique(sources, needle);
+    };
+    let found = Some(needle)
+        .filter(|n| !n.is_empty())
+        .and_then(|n| source.text[range.clone()].find(n));
+    match found {
+        Some(idx) => diagnostic_at(source, Some(range.start + idx)),
+        None => Diagnostic {
+            column: None,
+            ..diagnostic_at(source, Some(range.start))
+        },
+    }
+}
+
+/// 支持 `scheme://host/path`、scp风格的 `user@host:path` 和本地路径（需要存在）
+fn check_url(url: &str) -> Result<(), String> {
+    if url.trim().is_empty() {
+        return Err("url is empty".to_string());
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Err(format!("url contains whitespace: {}", url));
+    }
+    if let Some((scheme, rest)) = url.split_once("://") {
+        if !["http", "https", "ssh", "git", "file"].contains(&scheme) {
+            return Err(format!("unsupported url scheme `{}`: {}", scheme, url));
+        }
+        let host = rest.split('/').next().unwrap_or("");
+        if scheme != "file" && host.rsplit('@').next().unwrap_or("").is_empty() {
+            return Err(format!("url has no host: {}", url));
+        }
+        return Ok(());
+    }
+    // scp风格，冒号在第一个 / 之前
+    if let Some((host, path)) = url.split_once(':') {
+        if !host.contains('/') && host.len() > 1 {
+            if path.is_empty() {
+                return Err(format!("url has no path: {}", url));
+            }
+            return Ok(());
+        }
+    }
+    if !Path::new(url).exists() {
+        return Err(format!("local repository not found: {}", url));
+    }
+    Ok(())
+}
+
+/// libgit2 pathspec，`!pattern` 排除，fnmatch语法，不支持git的 `:(magic)`
+fn check_pathspec(pattern: &str) -> Result<(), String> {
+    let glob = pattern.strip_prefix('!').unwrap_or(pattern);
+    if glob.is_empty() {
+        return Err(format!("empty pathspec: `{}`", pattern));
+    }
+    if glob.starts_with(':') {
+        return Err(format!(
+            "pathspec magic is not supported, use `!pattern` to exclude: `{}`",
+            pattern
+        ));
+    }
+    let mut in_bracket = false;
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' if !in_bracket => {
+                in_bracket = true;
+                // `[]]` 和 `[!]]` 中的 ] 是字符本身
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                }
+            }
+            ']' if in_bracket => in_bracket = false,
+            _ => {}
+        }
+    }
+    if in_bracket {
+        return Err(format!("unclosed `[` in pathspec: `{}`", pattern));
+    }
+    Ok(())
+}
+
+/// repo名是 `./repos` 下的相对路径，用 `/` 分隔，不能跳出 `./repos`
+fn check_repo_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("repo name is empty".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| "\\:*?\"<>|".contains(*c) || c.is_control())
+    {
+        return Err(format!("repo name `{}` contains `{}`", name, c));
+    }
+    if name
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err(format!(
+            "repo name `{}` should be a relative path like group/api without empty, `.` or `..` parts",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// 正则语法错误有多行，最后一行是原因
+fn check_regex(pattern: &str) -> Result<(), String> {
+    regex::Regex::new(pattern).map(|_| ()).map_err(|e| {
+        let e = e.to_string();
+        let reason = e.lines().last().unwrap_or("");
+        let reason = reason.strip_prefix("error: ").unwrap_or(reason);
+        format!("invalid regex `{}`: {}", pattern, reason)
+    })
+}
+
+/// alias/bots规则的值
+fn check_matcher(kind: MatchKind, value: &str) -> Option<String> {
+    match kind {
+        MatchKind::Regex => check_regex(value).err(),
+        MatchKind::Email if !value.contains('@') => Some(format!("invalid email `{}`", value)),
+        MatchKind::EmailDomain if value.contains('@') || value.is_empty() => Some(format!(
+            "email_domain `{}` should be a domain like corp.com",
+            value
+        )),
+        _ => None,
+    }
+}
+
+type DateRange = (Option<NaiveDate>, Option<NaiveDate>);
+
+/// 两个日期范围(包含两端，None为不限)是否有交集
+fn overlaps(a: DateRange, b: DateRange) -> bool {
+    let starts_before_end =
+        |since: Option<NaiveDate>, until: Option<NaiveDate>| match (since, until) {
+            (Some(since), Some(until)) => since <= until,
+            _ => true,
+        };
+    starts_before_end(a.0, b.1) && starts_before_end(b.0, a.1)
+}
+
+/// 检查所有repo和作者配置，`sources` 用于定位
+pub(crate) fn validate(config: &Config, sources: &[Source]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut push = |location: Diagnostic, message: String| {
+        diagnostics.push(Diagnostic {
+            message,
+            ..location
+        });
+    };
+
+    let mut url_seen: HashMap<&str, usize> = HashMap::new();
+    let mut names: Vec<(String, &str)> = vec![];
+    for (i, repo) in config.repos.iter().enumerate() {
+        let nth = *url_seen
+            .entry(repo.url.as_str())
+            .and_modify(|n| *n += 1)
+            .or_default();
+        let at = |s: &str| format!("repos[{}]: {}", i, s);
+        let in_repo = |needle: &str| locate_entry(sources, "repos", &repo.url, nth, needle);
+        if let Err(e) = check_url(&repo.url) {
+            push(in_repo(&repo.url), at(&e));
+            continue;
+        }
+        let name = repo.repo_name();
+        let name_problem = match &repo.name {
+            Some(name) => check_repo_name(name).err(),
+            None if name.is_empty() => Some(format!("no repo name in url: {}", repo.url)),
+            None => None,
+        };
+        let nested = |a: &str, b: &str| b.starts_with(a) && b[a.len()..].starts_with('/');
+        if let Some(problem) = name_problem {
+            push(in_repo(&repo.url), at(&problem));
+        } else if let Some((other_name, other)) = names
+            .iter()
+            .find(|(n, _)| *n == name || nested(n, &name) || nested(&name, n))
+        {
+            let problem = if *other_name == name {
+                format!(
+                    "duplicate repo name `{}`, same as {}, both would be cloned to ./repos/{}",
+                    name, other, name
+                )
+            } else {
+                format!(
+                    "repo name `{}` and `{}` of {} would be cloned into each other",
+                    name, other_name, other
+                )
+            };
+            push(
+                in_repo(&repo.url),
+                at(&format!("{}, set a different `name`", problem)),
+            );
+        }
+        names.push((name, repo.url.as_str()));
+        let password_sources = repo.password_sources();
+        if password_sources.len() > 1 {
+            push(
+                in_repo(&repo.url),
+                at(&format!(
+                    "only one of {} can be set",
+                    password_sources.join(", ")
+                )),
+            );
+        }
+        if repo.branches.is_empty() {
+            push(in_repo(&repo.url), at("no branches"));
+        }
+        let patterns = repo
+            .pathspec
+            .iter()
+            .chain(repo.categories.iter().flat_map(|c| c.patterns.iter()));
+        for pattern in patterns {
+            if let Err(e) = check_pathspec(pattern) {
+                push(in_repo(pattern), at(&e));
+            }
+        }
+        if let (Some(since), Some(until)) = (repo.since, repo.until) {
+            if since > until {
+                push(
+                    in_repo(&repo.url),
+                    at(&format!("since {} is after until {}", since, until)),
+                );
+            }
+        }
+        for pattern in repo.exclude_emails.iter().chain(&repo.ignore_messages) {
+            if let Err(e) = check_regex(pattern) {
+                push(in_repo(pattern), at(&e));
+            }
+        }
+    }
+
+    if let Some(Err(e)) = config.timezone.as_deref().map(parse_timezone) {
+        push(
+            locate_unique(sources, config.timezone.as_deref().unwrap()),
+            format!("timezone: {}", e),
+        );
+    }
+    if let Some(Err(e)) = config.loc_interval.as_deref().map(str::parse::<Interval>) {
+        push(
+            locate_unique(sources, config.loc_interval.as_deref().unwrap()),
+            format!("loc_interval: {}", e),
+        );
+    }
+    let hours = &config.business_hours;
+    if !(hours.start < hours.end && hours.end <= 24) {
+        push(
+            locate_unique(sources, "business_hours:"),
+            format!(
+                "business_hours: start {} should be before end {}, and end at most 24",
+                hours.start, hours.end
+            ),
+        );
+    }
+    if let Some(day) = hours.workdays.iter().find(|d| !(1..=7).contains(*d)) {
+        push(
+            locate_unique(sources, "workdays:"),
+            format!(
+                "business_hours.workdays: {} should be 1 (Monday) to 7 (Sunday)",
+                day
+            ),
+        );
+    }
+    let min_degree = config.coupling.min_degree;
+    if !(0.0..=1.0).contains(&min_degree) {
+        push(
+            locate_unique(sources, "min_degree:"),
+            format!("coupling.min_degree: {} should be in [0, 1]", min_degree),
+        );
+    }
+    let threshold = config.bus_factor.threshold;
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        push(
+            locate_unique(sources, "threshold:"),
+            format!("bus_factor.threshold: {} should be in (0, 1]", threshold),
+        );
+    }
+    if let Some(p) = config.outliers.percentile {
+        if !(p > 0.0 && p <= 1.0) {
+            push(
+                locate_unique(sources, "percentile:"),
+                format!("outliers.percentile: {} should be in (0, 1], e.g. 0.99", p),
+            );
+        }
+    }
+    if let Some(Err(e)) = config
+        .message_lint
+        .issue_pattern
+        .as_deref()
+        .map(check_regex)
+    {
+        push(
+            locate_unique(
+                sources,
+                config.message_lint.issue_pattern.as_deref().unwrap(),
+            ),
+            format!("message_lint.issue_pattern: {}", e),
+        );
+    }
+
+    let mut bots_seen: HashMap<&str, usize> = HashMap::new();
+    for bot in &config.bots {
+        match bot.matcher() {
+            Ok((kind, value)) => {
+                let nth = *bots_seen.entry(value).and_modify(|n| *n += 1).or_default();
+                if let Some(problem) = check_matcher(kind, value) {
+                    let location = locate_entry(sources, "bots", value, nth, value);
+                    push(location, format!("bots: {}", problem));
+                }
+            }
+            Err(e) => push(locate_unique(sources, "bots:"), format!("bots: {}", e)),
+        }
+    }
+
+    // 同一个名字或email在重叠的日期范围内只能对应一个作者
+    let mut authors_seen: HashMap<&str, usize> = HashMap::new();
+    let mut claimed: Vec<(MatchKind, String, DateRange, &str)> = vec![];
+    for author in &config.authors {
+        let nth = *authors_seen
+            .entry(author.name.as_str())
+            .and_modify(|n| *n += 1)
+            .or_default();
+        let in_author = |needle: &str| locate_entry(sources, "authors", &author.name, nth, needle);
+        for alias in &author.alias {
+            let (kind, value) = match alias.matcher() {
+                Ok(matcher) => matcher,
+                Err(e) => {
+                    push(
+                        in_author(&author.name),
+                        format!("authors `{}`: {}", author.name, e),
+                    );
+                    continue;
+                }
+            };
+            let dates = alias.dates();
+            if let (Some(since), Some(until)) = dates {
+                if since > until {
+                    push(
+                        in_author(value),
+                        format!("`{}`: since {} is after until {}", value, since, until),
+                    );
+                }
+            }
+            if let Some(problem) = check_matcher(kind, value) {
+                push(in_author(value), problem);
+            }
+
+            let key = match kind {
+                MatchKind::Email | MatchKind::EmailDomain => value.to_lowercase(),
+                MatchKind::Name | MatchKind::Regex => value.to_string(),
+            };
+            if let Some((_, _, _, other)) = claimed.iter().find(|(k, v, d, other)| {
+                *k == kind && *v == key && *other != author.name && overlaps(*d, dates)
+            }) {
+                let label = match kind {
+                    MatchKind::Name => "alias",
+                    kind => kind.as_str(),
+                };
+                push(
+                    in_author(value),
+                    format!(
+                        "{} `{}` is mapped to both `{}` and `{}`",
+                        label, value, other, author.name
+                    ),
+                );
+            }
+            claimed.push((kind, key, dates, author.name.as_str()));
+        }
+    }
+
+    // 同一个作者在重叠的日期范围内只能属于一个团队
+    let authors: HashSet<&str> = config.authors.iter().map(|a| a.name.as_str()).collect();
+    let mut paths = HashSet::new();
+    let mut memberships: Vec<(&str, DateRange, String)> = vec![];
+    for (path, team) in Team::flatten(&config.teams) {
+        let needle = format!("name: {}", team.name);
+        if team.name.is_empty() || team.name.contains('/') {
+            push(
+                locate_unique(sources, &needle),
+                format!("team name `{}` should be non-empty without `/`", team.name),
+            );
+        }
+        if !paths.insert(path.clone()) {
+            push(
+                locate_unique(sources, &needle),
+                format!("duplicate team `{}`", path),
+            );
+        }
+        for member in &team.members {
+            let author = member.author();
+            if !authors.contains(author) {
+                push(
+                    locate_unique(sources, &needle),
+                    format!(
+                        "team `{}`: unknown author `{}`, members should be names in authors",
+                        path, author
+                    ),
+                );
+            }
+            let dates = member.dates();
+            if let (Some(since), Some(until)) = dates {
+                if since > until {
+                    push(
+                        locate_unique(sources, &needle),
+                        format!(
+                            "team `{}`: `{}` since {} is after until {}",
+                            path, author, since, until
+                        ),
+                    );
+                }
+            }
+            if let Some((_, _, other)) = memberships
+                .iter()
+                .find(|(a, d, _)| *a == author && overlaps(*d, dates))
+            {
+                push(
+                    locate_unique(sources, &needle),
+                    format!(
+                        "`{}` is a member of both `{}` and `{}` at the same time",
+                        author, other, path
+                    ),
+                );
+            }
+            memberships.push((author, dates, path.clone()));
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_url() {
+        for url in [
+            "https://github.com/26huitailang/yogo.git",
+            "ssh://git@gitlab.example.com:2222/g/p.git",
+            "git@github.com:26huitailang/yogo.git",
+            ".",
+        ] {
+            assert!(check_url(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "",
+            "htps://github.com/x.git",
+            "https:///x.git",
+            "github.com/x y.git",
+            "./no/such/repo",
+        ] {
+            assert!(check_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_check_pathspec() {
+        for p in ["*.go", "!vendor", "src/[abc]*.rs", "[]]x", "a\\[b"] {
+            assert!(check_pathspec(p).is_ok(), "{}", p);
+        }
+        for p in ["!", "", "src/[ab", ":(exclude)vendor"] {
+            assert!(check_pathspec(p).is_err(), "{}", p);
+        }
+    }
+
+    #[test]
+    fn test_check_repo_name() {
+        assert!(check_repo_name("github.com/group-a/api").is_ok());
+        assert!(check_repo_name("my.service").is_ok());
+        for name in ["", "/api", "a//b", "../api", "a/./b", "api/", "c:\\api"] {
+            assert!(check_repo_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let d = |s: &str| Some(s.parse::<NaiveDate>().unwrap());
+        assert!(overlaps((None, None), (d("2024-01-01"), None)));
+        assert!(overlaps((None, d("2024-01-01")), (d("2024-01-01"), None)));
+        assert!(!overlaps((None, d("2023-12-31")), (d("2024-01-01"), None)));
+        assert!(!overlaps(
+            (d("2024-03-01"), d("2024-03-31")),
+            (d("2024-01-01"), d("2024-02-29"))
+        ));
+    }
+
+    #[test]
+    fn test_locate() {
+        let source = |text: &str| Source {
+            file: PathBuf::from("x.yml"),
+            text: text.to_string(),
+        };
+        let position = |d: Diagnostic| (d.line, d.column);
+        let sources = [source("a: x\nb:\n  - y\n")];
+        assert_eq!(position(locate_unique(&sources, "y")), (Some(3), Some(5)));
+        assert_eq!(position(locate_unique(&sources, "x")), (Some(1), Some(4)));
+        assert_eq!(position(locate_unique(&sources, ":")), (None, None));
+
+        let text = "\
+repos:
+  - url: a.git
+    pathspec: [\"*.go\"]
+  # comment
+  - url: ab.git
+    branches:
+      - main
+    pathspec: [\"*.go\"]
+authors:
+- name: bob
+";
+        assert_eq!(
+            entries(text, "repos")
+                .into_iter()
+                .map(|r| &text[r])
+                .collect::<Vec<_>>(),
+            [
+                "  - url: a.git\n    pathspec: [\"*.go\"]",
+                "  - url: ab.git\n    branches:\n      - main\n    pathspec: [\"*.go\"]"
+            ]
+        );
+        let sources = [source("repos: []\n"), source(text)];
+        let entry = |anchor: &str, needle: &str| {
+            position(locate_entry(&sources, "repos", anchor, 0, needle))
+        };
+        assert_eq!(entry("ab.git", "*.go"), (Some(8), Some(17)));
+        assert_eq!(entry("a.git", "*.go"), (Some(3), Some(17)));
+        assert_eq!(entry("a.git", "main"), (Some(2), None));
+        // no entry with this url
+        assert_eq!(entry("b.git", "*.go"), (None, None));
+        assert_eq!(
+            position(locate_entry(&sources, "authors", "bob", 0, "bob")),
+            (Some(10), Some(9))
+        );
+    }
+}
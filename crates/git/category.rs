@@ -0,0 +1,142 @@
+use config::{CategoryRule, FileCategory};
+use git2::{Pathspec, PathspecFlags};
+use std::path::Path;
+
+const DEFAULT_TEST: &[&str] = &[
+    "*_test.go",
+    "*_test.rs",
+    "tests/*",
+    "*/tests/*",
+    "test/*",
+    "*/test/*",
+    "__tests__/*",
+    "*/__tests__/*",
+    "*.spec.ts",
+    "*.spec.tsx",
+    "*.spec.js",
+    "*.spec.jsx",
+    "*.test.ts",
+    "*.test.tsx",
+    "*.test.js",
+    "*.test.jsx",
+    "test_*.py",
+    "*/test_*.py",
+    "*_test.py",
+    "*Test.java",
+    "*Tests.java",
+    "*Test.kt",
+];
+
+// 不包含 `*.txt`，requirements.txt、CMakeLists.txt 这类构建文件不算文档
+const DEFAULT_DOCS: &[&str] = &[
+    "*.md",
+    "*.rst",
+    "*.adoc",
+    "README*",
+    "*/README*",
+    "docs/*",
+    "*/docs/*",
+    "doc/*",
+    "*/doc/*",
+    "LICENSE*",
+];
+
+const DEFAULT_CONFIG: &[&str] = &[
+    "*.yml",
+    "*.yaml",
+    "*.toml",
+    "*.json",
+    "*.ini",
+    "*.cfg",
+    "*.conf",
+    "*.xml",
+    "*.properties",
+    "*.lock",
+    "go.mod",
+    "go.sum",
+    "requirements*.txt",
+    "*/requirements*.txt",
+    "CMakeLists.txt",
+    "*/CMakeLists.txt",
+    "Dockerfile",
+    "*/Dockerfile",
+    "Makefile",
+    "*/Makefile",
+    ".*",
+    "*/.*",
+];
+
+/// 按规则给变更的文件分类，repo配置的规则优先，然后是内置规则，都不匹配的算production
+pub struct FileClassifier {
+    rules: Vec<(FileCategory, Pathspec)>,
+}
+
+impl FileClassifier {
+    pub fn new(custom: &[CategoryRule]) -> Result<Self, git2::Error> {
+        let mut rules = vec![];
+        for rule in custom {
+            rules.push((rule.category, Pathspec::new(rule.patterns.iter())?));
+        }
+        for (category, patterns) in [
+            (FileCategory::Test, DEFAULT_TEST),
+            (FileCategory::Docs, DEFAULT_DOCS),
+            (FileCategory::Config, DEFAULT_CONFIG),
+        ] {
+            rules.push((category, Pathspec::new(patterns.iter())?));
+        }
+        Ok(FileClassifier { rules })
+    }
+
+    pub fn classify(&self, path: &Path) -> FileCategory {
+        self.rules
+            .iter()
+            .find(|(_, spec)| spec.matches_path(path, PathspecFlags::DEFAULT))
+            .map(|(category, _)| *category)
+            .unwrap_or(FileCategory::Production)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(classifier: &FileClassifier, path: &str) -> FileCategory {
+        classifier.classify(Path::new(path))
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let c = FileClassifier::new(&[]).unwrap();
+        assert_eq!(classify(&c, "pkg/user/user_test.go"), FileCategory::Test);
+        assert_eq!(classify(&c, "tests/parse.rs"), FileCategory::Test);
+        assert_eq!(
+            classify(&c, "crates/git/tests/fixture.rs"),
+            FileCategory::Test
+        );
+        assert_eq!(classify(&c, "web/src/app.spec.ts"), FileCategory::Test);
+        assert_eq!(classify(&c, "pkg/test_api.py"), FileCategory::Test);
+        assert_eq!(classify(&c, "README.md"), FileCategory::Docs);
+        assert_eq!(classify(&c, "docs/images/tui.png"), FileCategory::Docs);
+        assert_eq!(classify(&c, "pkg/README"), FileCategory::Docs);
+        assert_eq!(classify(&c, "requirements.txt"), FileCategory::Config);
+        assert_eq!(classify(&c, "src/CMakeLists.txt"), FileCategory::Config);
+        assert_eq!(classify(&c, "testdata/input.txt"), FileCategory::Production);
+        assert_eq!(classify(&c, "Cargo.toml"), FileCategory::Config);
+        assert_eq!(classify(&c, "deploy/Dockerfile"), FileCategory::Config);
+        assert_eq!(classify(&c, ".gitignore"), FileCategory::Config);
+        assert_eq!(classify(&c, "pkg/user/user.go"), FileCategory::Production);
+        // test rules win over config rules
+        assert_eq!(classify(&c, "tests/data.json"), FileCategory::Test);
+    }
+
+    #[test]
+    fn test_custom_rules_first() {
+        let c = FileClassifier::new(&[CategoryRule {
+            category: FileCategory::Production,
+            patterns: vec!["api/*.json".to_string()],
+        }])
+        .unwrap();
+        assert_eq!(classify(&c, "api/swagger.json"), FileCategory::Production);
+        assert_eq!(classify(&c, "conf/app.json"), FileCategory::Config);
+    }
+}
@@ -1,41 +1,26 @@
-use config;
-use chrono::{DateTime, Local, TimeZone};
-use git2::{Cred, Diff, DiffOptions, RemoteCallbacks, Repository};
-use log::{debug, info, trace, warn};
+use crate::backend::{repo_dir, GitCliBackend, Libgit2Backend, RawCommit, REPOS_DIR};
+use crate::category::FileClassifier;
+use crate::merge::MergeInfo;
+use crate::signature::SignatureVerifier;
+use chrono::{DateTime, FixedOffset};
+use config::FileCategory;
+use git2::Repository;
+use log::{info, trace};
 use serde::{Serialize, Serializer};
 use std::error::Error;
 use std::io::{Cursor, Write};
 use std::path::Path;
 
-fn clone_or_open_repo(
-    url: &str,
-    into: &str,
-    repo_conf: config::Repo,
-) -> Result<Repository, git2::Error> {
-    if Path::new(into).exists() {
-        Repository::open(into)
-    } else {
-        let mut callbacks = RemoteCallbacks::new();
-        callbacks.credentials(|_url, _username_from_url, _allowed_types| {
-            Cred::userpass_plaintext(repo_conf.username(), repo_conf.password())
-        });
-        // Prepare fetch options.
-        let mut fo = git2::FetchOptions::new();
-        fo.remote_callbacks(callbacks);
+/// detail中的时间统一按UTC输出，原始时区偏移见`tz_offset`
+pub const DETAIL_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
-        let mut builder = git2::build::RepoBuilder::new();
-        builder.fetch_options(fo);
-        builder.clone(url, into.as_ref())
-    }
-}
-
-pub fn serialize_dt<S>(dt: &Option<DateTime<Local>>, serializer: S) -> Result<S::Ok, S::Error>
+pub fn serialize_dt<S>(dt: &Option<DateTime<FixedOffset>>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     match dt {
         Some(dt) => {
-            let s = dt.format("%Y-%m-%d %H:%M:%S").to_string();
+            let s = dt.naive_utc().format(DETAIL_DATE_FORMAT).to_string();
             serializer.serialize_str(s.as_str())
         }
         None => {
@@ -52,42 +37,57 @@ pub struct CommitInfo {
         serialize_with = "serialize_dt",
         skip_serializing_if = "Option::is_none"
     )]
-    pub date: Option<DateTime<Local>>,
+    pub date: Option<DateTime<FixedOffset>>,
+    /// commit时区相对UTC的偏移，单位分钟，同git的`+0800` => 480
+    pub tz_offset: i32,
     pub branch: String,
     pub commit_id: String,
     pub author: String,
+    pub email: String,
     pub message: String,
     pub insertions: usize,
     pub deletions: usize,
+    /// stable patch-id of the (pathspec filtered) diff, same change => same id,
+    /// used to group cherry-picks across branches and forks
+    pub patch_id: String,
+    /// commit是否有gpg/ssh签名
+    pub signed: bool,
+    /// 签名是否通过验证，没有签名或者没有配置对应的公钥时为空
+    pub signature_valid: Option<bool>,
+    /// 每个变更文件的统计
+    #[serde(skip)]
+    pub files: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    #[serde(serialize_with = "serialize_category")]
+    pub category: FileCategory,
+}
+
+fn serialize_category<S>(category: &FileCategory, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(category.as_str())
 }
 
 impl CommitInfo {
-    fn new(
-        repo: String,
-        date: Option<DateTime<Local>>,
-        branch: String,
-        commit_id: String,
-        author: String,
-        message: String,
-        insertions: usize,
-        deletions: usize,
-    ) -> Self {
-        CommitInfo {
-            repo,
-            date,
-            branch,
-            commit_id,
-            author,
-            message,
-            insertions,
-            deletions,
-        }
+    /// 某一类文件的 (insertions, deletions)
+    pub fn category_stats(&self, category: FileCategory) -> (usize, usize) {
+        self.files
+            .iter()
+            .filter(|f| f.category == category)
+            .fold((0, 0), |(i, d), f| (i + f.insertions, d + f.deletions))
     }
 
     pub fn format_datetime(&self) -> String {
         match &self.date {
             None => "".to_string(),
-            Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+            Some(datetime) => datetime.naive_utc().format(DETAIL_DATE_FORMAT).to_string(),
         }
     }
 }
@@ -104,183 +104,294 @@ impl CommitInfoVec {
 
     pub fn file_cursor(&self) -> Result<Cursor<Vec<u8>>, std::io::Error> {
         let mut w = csv::Writer::from_writer(Cursor::new(Vec::new()));
-        w.write_record(&[
+        let mut header = vec![
             "repo".to_string(),
             "date".to_string(),
+            "tz_offset".to_string(),
             "branch".to_string(),
             "commit_id".to_string(),
             "author".to_string(),
+            "email".to_string(),
             "message".to_string(),
             "insertions".to_string(),
             "deletions".to_string(),
-        ])
-        .unwrap();
+            "patch_id".to_string(),
+            "signed".to_string(),
+            "signature_valid".to_string(),
+        ];
+        for category in FileCategory::ALL {
+            header.push(format!("{}_insertions", category.as_str()));
+            header.push(format!("{}_deletions", category.as_str()));
+        }
+        w.write_record(&header).unwrap();
 
         for commit_info in &self.commit_info_vec {
-            w.write_record(&[
+            let mut record = vec![
                 commit_info.repo.to_string(),
                 commit_info.format_datetime(),
+                commit_info.tz_offset.to_string(),
                 commit_info.branch.to_string(),
                 commit_info.commit_id.to_string(),
                 commit_info.author.to_string(),
+                commit_info.email.to_string(),
                 commit_info.message.to_string(),
                 commit_info.insertions.to_string(),
                 commit_info.deletions.to_string(),
-            ])
-            .unwrap();
+                commit_info.patch_id.to_string(),
+                commit_info.signed.to_string(),
+                commit_info
+                    .signature_valid
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+            ];
+            for category in FileCategory::ALL {
+                let (insertions, deletions) = commit_info.category_stats(category);
+                record.push(insertions.to_string());
+                record.push(deletions.to_string());
+            }
+            w.write_record(&record).unwrap();
         }
         let mut cursor = w.into_inner().unwrap();
         cursor.flush()?;
         Ok(cursor)
     }
+
+    /// 文件级别的detail，一行一个commit中变更的文件
+    pub fn files_cursor(&self) -> Result<Cursor<Vec<u8>>, std::io::Error> {
+        let mut w = csv::Writer::from_writer(Cursor::new(Vec::new()));
+        w.write_record([
+            "repo",
+            "branch",
+            "commit_id",
+            "path",
+            "category",
+            "insertions",
+            "deletions",
+        ])
+        .unwrap();
+
+        for commit_info in &self.commit_info_vec {
+            for file in &commit_info.files {
+                w.write_record(&[
+                    commit_info.repo.to_string(),
+                    commit_info.branch.to_string(),
+                    commit_info.commit_id.to_string(),
+                    file.path.to_string(),
+                    file.category.as_str().to_string(),
+                    file.insertions.to_string(),
+                    file.deletions.to_string(),
+                ])
+                .unwrap();
+            }
+        }
+        let mut cursor = w.into_inner().unwrap();
+        cursor.flush()?;
+        Ok(cursor)
+    }
+}
+
+/// clone到 `./repos/<repo_name>`，已经存在时直接打开
+///
+/// loc统计直接用libgit2读本地clone，`cli` backend的repo仍由系统git clone
+pub fn open_repo(repo_conf: &config::Repo) -> Result<Repository, git2::Error> {
+    let dir = repo_dir(Path::new(REPOS_DIR), repo_conf);
+    let (url, username, password) = (
+        repo_conf.url.as_str(),
+        repo_conf.username(),
+        repo_conf.password(),
+    );
+    if repo_conf.backend == config::GitBackend::Cli {
+        GitCliBackend::clone_or_open(url, &dir, username, password)
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    }
+    Libgit2Backend::clone_or_open(url, &dir, username, password).map(|b| b.repo)
+}
+
+/// 一个repo解析出的commit和合并
+#[derive(Debug, Clone, Default)]
+pub struct RepoData {
+    pub commits: Vec<CommitInfo>,
+    pub merges: Vec<MergeInfo>,
+}
+
+/// backend读出的commit转成detail的一行：文件分类、验证签名
+pub fn commit_info(
+    repo_name: &str,
+    branch: &str,
+    raw: RawCommit,
+    classifier: &FileClassifier,
+    verifier: &SignatureVerifier,
+) -> CommitInfo {
+    let signature = verifier.verify(&raw.id, raw.signature.as_ref());
+    let files: Vec<FileChange> = raw
+        .files
+        .into_iter()
+        .map(|f| FileChange {
+            category: classifier.classify(Path::new(&f.path)),
+            path: f.path,
+            insertions: f.insertions,
+            deletions: f.deletions,
+        })
+        .collect();
+    let insertions = files.iter().map(|f| f.insertions).sum();
+    let deletions = files.iter().map(|f| f.deletions).sum();
+    trace!(
+        "repo: {} commit: {} | {} | {} | {} | +{} | -{} | {}",
+        repo_name,
+        raw.time.to_rfc3339(),
+        branch,
+        raw.id,
+        raw.author,
+        insertions,
+        deletions,
+        raw.message.lines().next().unwrap_or(""),
+    );
+    CommitInfo {
+        repo: repo_name.to_string(),
+        date: Some(raw.time),
+        tz_offset: raw.time.offset().local_minus_utc() / 60,
+        branch: branch.to_string(),
+        commit_id: raw.id,
+        author: raw.author,
+        email: raw.email,
+        message: raw.message,
+        insertions,
+        deletions,
+        patch_id: raw.patch_id,
+        signed: signature.signed,
+        signature_valid: signature.valid,
+        files,
+    }
 }
 
 pub fn repo_parse(
     repo_conf: &config::Repo,
+    signatures: &config::SignatureOptions,
     update: bool,
-) -> Result<Vec<CommitInfo>, Box<dyn Error>> {
-    let url = repo_conf.url.as_str();
-    let into = format!("./repos/{}", repo_conf.repo_name());
-
-    let repo = match clone_or_open_repo(url, into.as_str(), repo_conf.clone()) {
-        Ok(repo) => repo,
-        Err(e) => panic!("Failed to clone repository: {}", e),
-    };
-
-    info!("clone/open repository: {}", repo.path().display());
+) -> Result<RepoData, Box<dyn Error>> {
+    repo_parse_in(Path::new(REPOS_DIR), repo_conf, signatures, update)
+}
 
-    let mut commit_data: Vec<CommitInfo> = Vec::new();
-    // 切换到指定分支
-    for b in &repo_conf.branches {
-        let branch_name = b.as_str();
-        let _ = repo.find_remote("origin").expect("remote not found");
-        let args = crate::repo::Args {
-            arg_remote: Some("origin".to_string()),
-            arg_branch: Some(branch_name.to_string()),
-        };
+/// 同 `repo_parse`，clone到 `<repos>/<repo_name>`
+pub fn repo_parse_in(
+    repos: &Path,
+    repo_conf: &config::Repo,
+    signatures: &config::SignatureOptions,
+    update: bool,
+) -> Result<RepoData, Box<dyn Error>> {
+    let backend = crate::backend::open_in(repos, repo_conf)?;
+    let classifier = FileClassifier::new(&repo_conf.categories)?;
+    let verifier = SignatureVerifier::new(signatures);
+    let repo_name = &repo_conf.repo_name();
 
+    let mut data = RepoData::default();
+    for branch in &repo_conf.branches {
         if update {
-            crate::repo::pull(&args, &repo, repo_conf.username(), repo_conf.password())
-                .expect("git pull failed");
+            backend.fetch(branch)?;
         }
-        // print current branch  and commit ref
-        let _ = repo.set_head(format!("refs/remotes/origin/{}", branch_name).as_str());
-        repo.checkout_head(Some(
-            git2::build::CheckoutBuilder::default()
-                // For some reason the force is required to make the working directory actually get updated
-                // I suspect we should be adding some logic to handle dirty working directory states
-                // but this is just an example so maybe not.
-                .force(),
-        ))
-        .expect(format!("checkout failed: {}/{}", repo_conf.repo_name(), branch_name).as_str());
-
-        let (object, reference) = repo
-            .revparse_ext(repo.head().unwrap().name().unwrap())
-            .expect("branch not found");
-        repo.checkout_tree(&object, None).expect("checkout failed");
-        match reference {
-            Some(gref) => {
-                let _ = repo.set_head(gref.name().unwrap());
-                debug!("Checked out branch: {}", gref.name().unwrap());
-            }
-            None => {
-                debug!("this is a commit");
-                // 返回错误
-                return Err(Box::new(git2::Error::from_str("branch not found")));
-            }
+        info!("walk branch: {}/{}", repo_name, branch);
+        for raw in backend.commits(branch, &repo_conf.pathspec)? {
+            data.commits
+                .push(commit_info(repo_name, branch, raw, &classifier, &verifier));
         }
+        data.merges
+            .extend(backend.merges(repo_name, branch, &repo_conf.pathspec)?);
+    }
+    Ok(data)
+}
 
-        info!("walk branch: {}/{}", repo_conf.repo_name(), branch_name);
-        // 遍历这个branch上所有commit
-        let mut rev = repo.revwalk().unwrap();
-        rev.set_sorting(git2::Sort::TIME).unwrap();
-        rev.push_head().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{rename, write, Fixture};
 
-        let mut diff_options = DiffOptions::new();
-        // include suffix file type
-        for pathspec_str in &repo_conf.pathspec {
-            // warn: 这里 !framework 要写到其他类似 *.go 前面，否则不生效
-            diff_options.pathspec(pathspec_str);
-            debug!("pathspec set: {}", pathspec_str);
-        }
-        for oid in rev {
-            let commit = repo.find_commit(oid.unwrap()).unwrap();
-            if commit.parent_count() > 1 {
-                debug!(
-                    "commit has more than one parent, maybe merge commit, skip: {}",
-                    commit.id()
+    #[test]
+    fn test_repo_parse_and_pull() {
+        for backend in [config::GitBackend::Libgit2, config::GitBackend::Cli] {
+            let fixture = Fixture::new();
+            let mut remote = fixture.remote("api");
+            remote
+                .commit(
+                    "alice",
+                    "2024-01-01T10:00:00+08:00",
+                    "init",
+                    &[write("src/a.rs", "a\n"), write("docs/x.md", "x\n")],
+                )
+                .branch("feature")
+                .commit(
+                    "bob",
+                    "2024-01-02T10:00:00+08:00",
+                    "Move a",
+                    &[rename("src/a.rs", "src/b.rs")],
+                )
+                .checkout("main")
+                .merge(
+                    "feature",
+                    "alice",
+                    "2024-01-03T10:00:00+08:00",
+                    "Merge pull request #7 from bob/feature\n\nMove a",
                 );
-                continue;
-            }
-
-            let tree = commit.tree().unwrap();
-            let diff: Diff;
+            let mut repo_conf = remote.repo_conf(&["main"]);
+            repo_conf.backend = backend;
+            let signatures = config::SignatureOptions::default();
 
-            match commit.parent(0) {
-                Ok(parent) => {
-                    let parent_tree = parent.tree().unwrap();
-                    diff = repo
-                        .diff_tree_to_tree(Some(&parent_tree), Some(&tree), Some(&mut diff_options))
-                        .unwrap();
-                }
-                Err(_) => {
-                    debug!("no parent, try none diff");
-                    diff = repo
-                        .diff_tree_to_tree(None, Some(&tree), Some(&mut diff_options))
-                        .unwrap();
-                }
-            };
-            let stats = diff.stats().unwrap();
-            if stats.files_changed() == 0 {
-                debug!("no files changed, skip: {}", commit.id());
-                continue;
-            }
-
-            // 时间戳转换
-            let time = commit.time().seconds();
-            let datetime = Local::timestamp_opt(&Local, time, 0).unwrap();
+            let data = repo_parse_in(&fixture.repos_dir(), &repo_conf, &signatures, false).unwrap();
+            assert_eq!(data.commits.len(), 2, "{:?}", backend);
+            let moved = &data.commits[0];
+            assert_eq!((moved.author.as_str(), moved.tz_offset), ("bob", 480));
+            // 不检测重命名：删除+新增
+            assert_eq!((moved.insertions, moved.deletions), (1, 1));
+            assert_eq!(data.merges.len(), 1);
+            assert_eq!(data.merges[0].mr_id, "#7");
 
-            let author = commit.author().name().unwrap().to_string();
-            // let author = match repo_conf.map_alias_to_name(commit.author().name().clone().unwrap())
-            // {
-            //     Some(name) => name,
-            //     None => {
-            //         println!("no author name found, use author name");
-            //         commit.author().name().unwrap().to_string()
-            //     }
-            // };
-            trace!(
-                "repo: {} commit: {} | {} | {} | {} | +{} | -{} | {}",
-                repo_conf.repo_name(),
-                datetime.format("%Y-%m-%d %H:%M:%S"),
-                branch_name,
-                commit.id(),
-                author,
-                stats.insertions(),
-                stats.deletions(),
-                commit.summary().unwrap_or(""),
+            // 远程新增commit，不更新时看不到，更新后可以解析到
+            remote.commit(
+                "carol",
+                "2024-01-04T10:00:00+08:00",
+                "docs",
+                &[write("docs/x.md", "x\ny\n")],
             );
-            // append to data
-            let cmt_msg = match commit.message() {
-                Some(msg) => msg.to_string(),
-                None => {
-                    warn!("no commit message found, use empty string: {}", commit.id());
-                    "".to_string()
-                }
-            };
-            let commit_row = CommitInfo::new(
-                repo_conf.repo_name().to_string(),
-                datetime.into(),
-                branch_name.to_string(),
-                commit.id().to_string(),
-                author,
-                cmt_msg,
-                stats.insertions(),
-                stats.deletions(),
-            );
-            commit_data.push(commit_row);
+            let data = repo_parse_in(&fixture.repos_dir(), &repo_conf, &signatures, false).unwrap();
+            assert_eq!(data.commits.len(), 2, "{:?}", backend);
+            let data = repo_parse_in(&fixture.repos_dir(), &repo_conf, &signatures, true).unwrap();
+            assert_eq!(data.commits.len(), 3, "{:?}", backend);
+            assert_eq!(data.commits[0].author, "carol");
+            assert_eq!(data.commits[0].category_stats(FileCategory::Docs), (1, 0));
         }
     }
 
-    return Ok(commit_data);
+    #[test]
+    fn test_migrate_legacy_clone() {
+        let fixture = Fixture::new();
+        let mut remote = fixture.remote("api");
+        remote.commit(
+            "alice",
+            "2024-01-01T10:00:00+08:00",
+            "init",
+            &[write("a.rs", "a\n")],
+        );
+        let repo_conf = remote.repo_conf(&["main"]);
+        let signatures = config::SignatureOptions::default();
+        let repos = fixture.repos_dir();
+        let legacy = repos.join("api");
+        let dir = repos.join(repo_conf.repo_name());
+
+        // 另一个同名repo的旧clone不动
+        let other = git2::Repository::init(&legacy).unwrap();
+        other
+            .remote("origin", "https://example.com/g/api.git")
+            .unwrap();
+        drop(other);
+        repo_parse_in(&repos, &repo_conf, &signatures, false).unwrap();
+        assert!(legacy.join(".git").exists());
+        assert!(dir.exists());
+
+        std::fs::remove_dir_all(&legacy).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        git2::Repository::clone(&repo_conf.url, &legacy).unwrap();
+        let data = repo_parse_in(&repos, &repo_conf, &signatures, false).unwrap();
+        assert_eq!(data.commits.len(), 1);
+        assert!(!legacy.exists());
+        assert!(dir.join(".git").exists());
+    }
 }
@@ -1,2 +1,10 @@
+pub mod backend;
+pub mod category;
 pub mod commit;
+pub mod loc;
+pub mod merge;
 pub mod repo;
+pub mod signature;
+pub mod survey;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
@@ -0,0 +1,546 @@
+use crate::commit::open_repo;
+use chrono::{DateTime, Utc};
+use git2::{
+    ObjectType, Oid, Pathspec, PathspecFlags, Repository, Tree, TreeWalkMode, TreeWalkResult,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::AddAssign;
+use std::path::Path;
+
+struct Language {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    filenames: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+}
+
+const C_BLOCK: Option<(&str, &str)> = Some(("/*", "*/"));
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "tsx"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "C++",
+        extensions: &["cc", "cpp", "cxx", "hpp", "hh"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        filenames: &[],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "Python",
+        extensions: &["py"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb"],
+        filenames: &["Gemfile", "Rakefile"],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        filenames: &[],
+        line_comments: &["//", "#"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        filenames: &[],
+        line_comments: &["--"],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "vue"],
+        filenames: &[],
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "less"],
+        filenames: &[],
+        line_comments: &[],
+        block_comment: C_BLOCK,
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        filenames: &[],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json"],
+        filenames: &[],
+        line_comments: &[],
+        block_comment: None,
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md"],
+        filenames: &[],
+        line_comments: &[],
+        block_comment: None,
+    },
+    Language {
+        name: "Makefile",
+        extensions: &["mk"],
+        filenames: &["Makefile", "makefile", "GNUmakefile"],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+    Language {
+        name: "Dockerfile",
+        extensions: &[],
+        filenames: &["Dockerfile"],
+        line_comments: &["#"],
+        block_comment: None,
+    },
+];
+
+/// 不认识的文本文件，不区分注释
+const OTHER: Language = Language {
+    name: "Other",
+    extensions: &[],
+    filenames: &[],
+    line_comments: &[],
+    block_comment: None,
+};
+
+fn language_of(path: &Path) -> &'static Language {
+    let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    LANGUAGES
+        .iter()
+        .find(|l| l.filenames.contains(&file_name) || l.extensions.contains(&ext))
+        .unwrap_or(&OTHER)
+}
+
+/// 语言的文件扩展名，不认识的语言为空
+pub(crate) fn extensions_of(name: &str) -> &'static [&'static str] {
+    LANGUAGES
+        .iter()
+        .find(|l| l.name == name)
+        .map(|l| l.extensions)
+        .unwrap_or(&[])
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LineCounts {
+    pub files: usize,
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+}
+
+impl AddAssign for LineCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.files += other.files;
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// 按行分类，注释只认行首的注释符号，代码后面的行尾注释算代码
+fn count_lines(content: &str, language: &Language) -> LineCounts {
+    let mut counts = LineCounts {
+        files: 1,
+        ..Default::default()
+    };
+    let mut in_block: Option<&str> = None;
+    for line in content.lines() {
+        let t = line.trim();
+        if let Some(end) = in_block {
+            counts.comment += 1;
+            if t.contains(end) {
+                in_block = None;
+            }
+            continue;
+        }
+        if t.is_empty() {
+            counts.blank += 1;
+            continue;
+        }
+        if language.line_comments.iter().any(|c| t.starts_with(c)) {
+            counts.comment += 1;
+            continue;
+        }
+        if let Some((start, end)) = language.block_comment {
+            if let Some(rest) = t.strip_prefix(start) {
+                counts.comment += 1;
+                if !rest.contains(end) {
+                    in_block = Some(end);
+                }
+                continue;
+            }
+        }
+        counts.code += 1;
+    }
+    counts
+}
+
+/// 直接读tree中的blob统计各语言行数，不需要checkout，二进制文件跳过
+pub fn count_tree(
+    repo: &Repository,
+    tree: &Tree,
+    pathspec: &Pathspec,
+) -> Result<BTreeMap<&'static str, LineCounts>, git2::Error> {
+    let mut result: BTreeMap<&'static str, LineCounts> = BTreeMap::new();
+    let mut err = None;
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() != Some(ObjectType::Blob) {
+            return TreeWalkResult::Ok;
+        }
+        let path = format!("{}{}", root, entry.name().unwrap_or(""));
+        let path = Path::new(&path);
+        if !pathspec.matches_path(path, PathspecFlags::DEFAULT) {
+            return TreeWalkResult::Ok;
+        }
+        let blob = match repo.find_blob(entry.id()) {
+            Ok(blob) => blob,
+            Err(e) => {
+                err = Some(e);
+                return TreeWalkResult::Abort;
+            }
+        };
+        if blob.is_binary() {
+            return TreeWalkResult::Ok;
+        }
+        let language = language_of(path);
+        let content = String::from_utf8_lossy(blob.content());
+        *result.entry(language.name).or_default() += count_lines(&content, language);
+        TreeWalkResult::Ok
+    })?;
+    match err {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocStat {
+    pub repo: String,
+    pub branch: String,
+    pub commit_id: String,
+    pub language: String,
+    #[serde(flatten)]
+    pub counts: LineCounts,
+}
+
+/// 每个配置的分支head的代码行数，按repo的pathspec过滤
+pub fn snapshot(repo_conf: &config::Repo) -> Result<Vec<LocStat>, git2::Error> {
+    let repo = open_repo(repo_conf)?;
+    let pathspec = Pathspec::new(repo_conf.pathspec.iter())?;
+    let mut stats = vec![];
+    for branch in &repo_conf.branches {
+        let reference = repo.find_reference(&format!("refs/remotes/origin/{}", branch))?;
+        let commit = reference.peel_to_commit()?;
+        let counts = count_tree(&repo, &commit.tree()?, &pathspec)?;
+        for (language, counts) in counts {
+            stats.push(LocStat {
+                repo: repo_conf.repo_name().to_string(),
+                branch: branch.to_string(),
+                commit_id: commit.id().to_string(),
+                language: language.to_string(),
+                counts,
+            });
+        }
+    }
+    Ok(stats)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocPoint {
+    pub repo: String,
+    pub branch: String,
+    /// 采样时间点
+    pub date: DateTime<Utc>,
+    /// 该时间点分支上的commit
+    pub commit_id: String,
+    #[serde(flatten)]
+    pub counts: LineCounts,
+}
+
+/// 分支的first-parent链，(committer时间, oid)，从新到旧
+pub(crate) fn first_parent_chain(
+    repo: &Repository,
+    head: Oid,
+) -> Result<Vec<(i64, Oid)>, git2::Error> {
+    let mut rev = repo.revwalk()?;
+    rev.push(head)?;
+    rev.simplify_first_parent()?;
+    let mut chain = vec![];
+    for oid in rev {
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+        chain.push((commit.time().seconds(), oid));
+    }
+    Ok(chain)
+}
+
+/// 每个采样时间点分支上最新的commit，时间点早于第一个commit时为None
+///
+/// rebase或时钟偏差时链上的时间不一定递减，commit自己和更早的commit都不晚于时间点才算已经存在
+fn commits_at(chain: &[(i64, Oid)], boundaries: &[DateTime<Utc>]) -> Vec<Option<Oid>> {
+    // 从旧到新累计最大时间
+    let mut latest = vec![i64::MIN; chain.len()];
+    let mut max = i64::MIN;
+    for (i, (time, _)) in chain.iter().enumerate().rev() {
+        max = max.max(*time);
+        latest[i] = max;
+    }
+    boundaries
+        .iter()
+        .map(|b| {
+            chain
+                .iter()
+                .zip(&latest)
+                .find(|(_, latest)| **latest <= b.timestamp())
+                .map(|((_, oid), _)| *oid)
+        })
+        .collect()
+}
+
+/// 沿first-parent历史在每个时间点统计代码行数（所有语言合计）
+pub fn history(
+    repo_conf: &config::Repo,
+    boundaries: &[DateTime<Utc>],
+) -> Result<Vec<LocPoint>, git2::Error> {
+    let repo = open_repo(repo_conf)?;
+    let pathspec = Pathspec::new(repo_conf.pathspec.iter())?;
+    let mut points = vec![];
+    for branch in &repo_conf.branches {
+        let reference = repo.find_reference(&format!("refs/remotes/origin/{}", branch))?;
+        let head = reference.peel_to_commit()?.id();
+        let chain = first_parent_chain(&repo, head)?;
+        // 相邻时间点可能是同一个commit
+        let mut cache: HashMap<Oid, LineCounts> = HashMap::new();
+        for (date, oid) in boundaries.iter().zip(commits_at(&chain, boundaries)) {
+            let Some(oid) = oid else {
+                continue;
+            };
+            let counts = match cache.get(&oid) {
+                Some(counts) => *counts,
+                None => {
+                    let tree = repo.find_commit(oid)?.tree()?;
+                    let mut total = LineCounts::default();
+                    for counts in count_tree(&repo, &tree, &pathspec)?.into_values() {
+                        total += counts;
+                    }
+                    cache.insert(oid, total);
+                    total
+                }
+            };
+            points.push(LocPoint {
+                repo: repo_conf.repo_name().to_string(),
+                branch: branch.to_string(),
+                date: *date,
+                commit_id: oid.to_string(),
+                counts,
+            });
+        }
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{write, Fixture};
+
+    #[test]
+    fn test_language_of() {
+        assert_eq!(language_of(Path::new("pkg/main.go")).name, "Go");
+        assert_eq!(language_of(Path::new("web/app.tsx")).name, "TypeScript");
+        assert_eq!(
+            language_of(Path::new("deploy/Dockerfile")).name,
+            "Dockerfile"
+        );
+        assert_eq!(language_of(Path::new("Makefile")).name, "Makefile");
+        assert_eq!(language_of(Path::new("LICENSE")).name, "Other");
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let content = r#"// Package main
+package main
+
+/*
+ multi line
+*/
+import "fmt"
+
+/* one line */
+func main() {
+    fmt.Println("hi") // trailing comment is code
+}
+"#;
+        let counts = count_lines(content, language_of(Path::new("main.go")));
+        assert_eq!(
+            counts,
+            LineCounts {
+                files: 1,
+                code: 5,
+                comment: 5,
+                blank: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_tree() {
+        let fixture = Fixture::new();
+        let mut api = fixture.remote("api");
+        api.commit(
+            "alice",
+            "2024-01-01T10:00:00Z",
+            "init",
+            &[
+                write("cmd/main.go", "package main\n\n// main\nfunc main() {}\n"),
+                write("cmd/logo.png", [0u8, 159, 146, 150]),
+                write("README.md", "# title\n"),
+            ],
+        );
+        let repo = api.repository();
+        let head = repo.find_commit(api.head("main").unwrap()).unwrap();
+        let tree = head.tree().unwrap();
+
+        let all = count_tree(repo, &tree, &Pathspec::new(Vec::<String>::new()).unwrap()).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all["Go"].code, 2);
+        assert_eq!(all["Go"].comment, 1);
+        assert_eq!(all["Markdown"].files, 1);
+
+        let go_only = count_tree(repo, &tree, &Pathspec::new(["*.go"]).unwrap()).unwrap();
+        assert_eq!(go_only.keys().collect::<Vec<_>>(), vec![&"Go"]);
+        let excluded = count_tree(repo, &tree, &Pathspec::new(["!cmd", "*"]).unwrap()).unwrap();
+        assert_eq!(excluded.keys().collect::<Vec<_>>(), vec![&"Markdown"]);
+    }
+
+    #[test]
+    fn test_commits_at_out_of_order() {
+        let fixture = Fixture::new();
+        let mut api = fixture.remote("api");
+        api.commit(
+            "alice",
+            "2024-01-01T10:00:00Z",
+            "a",
+            &[write("a.go", "a\n")],
+        );
+        let a = api.head("main").unwrap();
+        api.commit(
+            "alice",
+            "2024-01-20T10:00:00Z",
+            "b",
+            &[write("b.go", "b\n")],
+        );
+        // rebased on top of b, keeps its original (earlier) date
+        api.commit("bob", "2024-01-05T10:00:00Z", "c", &[write("c.go", "c\n")]);
+        let c = api.head("main").unwrap();
+
+        let chain = first_parent_chain(api.repository(), c).unwrap();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().to_utc();
+        assert_eq!(
+            commits_at(
+                &chain,
+                &[
+                    at("2024-01-10T00:00:00Z"),
+                    at("2024-01-21T00:00:00Z"),
+                    at("2023-12-31T00:00:00Z")
+                ]
+            ),
+            vec![Some(a), Some(c), None]
+        );
+    }
+
+    #[test]
+    fn test_commits_at() {
+        let a = Oid::from_str("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+        let b = Oid::from_str("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb").unwrap();
+        // newest first
+        let chain = vec![(200, b), (100, a)];
+        let at = |ts: i64| DateTime::<Utc>::from_timestamp(ts, 0).unwrap();
+        assert_eq!(
+            commits_at(&chain, &[at(50), at(100), at(150), at(300)]),
+            vec![None, Some(a), Some(a), Some(b)]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use crate::backend::RawCommit;
+use crate::commit::{serialize_dt, DETAIL_DATE_FORMAT};
+use chrono::{DateTime, FixedOffset, TimeZone};
+use git2::{Commit, DiffOptions, Repository, Time};
+use regex::Regex;
+use serde::Serialize;
+use std::io::{Cursor, Write};
+use std::sync::LazyLock;
+
+static GITLAB_MR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^See merge request (?:\S+)?!(\d+)").unwrap());
+static GITHUB_PR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^Merge pull request #(\d+) from (\S+)").unwrap());
+static MERGE_BRANCH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^Merge (?:remote-tracking )?branch '([^']+)'(?: of \S+)?(?: into '?([^'\s]+)'?)?")
+        .unwrap()
+});
+
+/// 从merge commit的message中解析出的信息
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeMessage {
+    /// gitlab `!123`，github `#45`
+    pub id: Option<String>,
+    pub source_branch: Option<String>,
+    pub target_branch: Option<String>,
+    /// github/gitlab合并时message第二段是MR标题
+    pub title: Option<String>,
+}
+
+pub fn parse_merge_message(message: &str) -> MergeMessage {
+    let mut parsed = MergeMessage::default();
+    let subject = message.lines().next().unwrap_or("");
+    if let Some(caps) = GITHUB_PR.captures(subject) {
+        parsed.id = Some(format!("#{}", &caps[1]));
+        // owner/branch => branch
+        let from = &caps[2];
+        let branch = from.split_once('/').map(|(_, b)| b).unwrap_or(from);
+        parsed.source_branch = Some(branch.to_string());
+    } else if let Some(caps) = MERGE_BRANCH.captures(subject) {
+        let source = &caps[1];
+        let source = source.strip_prefix("origin/").unwrap_or(source);
+        parsed.source_branch = Some(source.to_string());
+        parsed.target_branch = caps.get(2).map(|m| m.as_str().to_string());
+    }
+    if let Some(caps) = GITLAB_MR.captures(message) {
+        parsed.id = Some(format!("!{}", &caps[1]));
+    }
+    if parsed.id.is_some() {
+        parsed.title = message
+            .split("\n\n")
+            .nth(1)
+            .map(|s| s.lines().next().unwrap_or("").trim().to_string())
+            .filter(|s| !s.is_empty() && !GITLAB_MR.is_match(s));
+    }
+    parsed
+}
+
+/// 由merge commit还原的一次合并(MR/PR)
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeInfo {
+    pub repo: String,
+    /// 遍历的分支，merge commit在它的first-parent历史上
+    pub branch: String,
+    /// merge commit
+    pub commit_id: String,
+    /// MR/PR编号，直接合并分支时为空
+    pub mr_id: String,
+    pub title: String,
+    pub source_branch: String,
+    pub target_branch: String,
+    /// merge commit的作者
+    pub merger: String,
+    #[serde(serialize_with = "serialize_dt")]
+    pub merged_at: Option<DateTime<FixedOffset>>,
+    /// 合并进来的commit中最早的author时间
+    #[serde(serialize_with = "serialize_dt")]
+    pub first_commit_at: Option<DateTime<FixedOffset>>,
+    /// 第二个parent可达、第一个parent不可达的commit数
+    pub commits: usize,
+    /// merge-base到第二个parent的diff，遵循pathspec
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// merge commit合并进来的范围
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeRange {
+    pub commits: usize,
+    pub first_commit_at: Option<DateTime<FixedOffset>>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+pub(crate) fn to_datetime(time: Time) -> Option<DateTime<FixedOffset>> {
+    FixedOffset::east_opt(time.offset_minutes() * 60)?
+        .timestamp_opt(time.seconds(), 0)
+        .single()
+}
+
+impl MergeInfo {
+    pub fn new(repo_name: &str, branch: &str, commit: &RawCommit, range: MergeRange) -> Self {
+        let message = parse_merge_message(&commit.message);
+        MergeInfo {
+            repo: repo_name.to_string(),
+            branch: branch.to_string(),
+            commit_id: commit.id.to_string(),
+            mr_id: message.id.unwrap_or_default(),
+            title: message.title.unwrap_or_default(),
+            source_branch: message.source_branch.unwrap_or_default(),
+            target_branch: message.target_branch.unwrap_or(branch.to_string()),
+            merger: commit.author.to_string(),
+            merged_at: Some(commit.time),
+            first_commit_at: range.first_commit_at,
+            commits: range.commits,
+            insertions: range.insertions,
+            deletions: range.deletions,
+        }
+    }
+
+    /// 还原一个merge commit合并的范围，不是merge commit时返回None
+    pub fn from_commit(
+        repo: &Repository,
+        commit: &Commit,
+        repo_name: &str,
+        branch: &str,
+        diff_options: &mut DiffOptions,
+    ) -> Result<Option<Self>, git2::Error> {
+        if commit.parent_count() < 2 {
+            return Ok(None);
+        }
+        let target = commit.parent(0)?;
+        let source = commit.parent(1)?;
+
+        let mut rev = repo.revwalk()?;
+        rev.push(source.id())?;
+        rev.hide(target.id())?;
+        let mut commits = 0;
+        let mut first_commit: Option<Time> = None;
+        for oid in rev {
+            let merged = repo.find_commit(oid?)?;
+            let when = merged.author().when();
+            if first_commit.is_none_or(|t| when.seconds() < t.seconds()) {
+                first_commit = Some(when);
+            }
+            commits += 1;
+        }
+
+        let base_tree = match repo.merge_base(target.id(), source.id()) {
+            Ok(base) => Some(repo.find_commit(base)?.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(
+            base_tree.as_ref(),
+            Some(&source.tree()?),
+            Some(diff_options),
+        )?;
+        let stats = diff.stats()?;
+
+        let range = MergeRange {
+            commits,
+            first_commit_at: first_commit.and_then(to_datetime),
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+        };
+        Ok(Some(MergeInfo::new(
+            repo_name,
+            branch,
+            &RawCommit::from_git2(commit),
+            range,
+        )))
+    }
+}
+
+fn format_dt(dt: &Option<DateTime<FixedOffset>>) -> String {
+    dt.map(|d| d.naive_utc().format(DETAIL_DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeInfoVec {
+    pub merge_info_vec: Vec<MergeInfo>,
+}
+
+impl MergeInfoVec {
+    pub fn new(merge_info_vec: Vec<MergeInfo>) -> Self {
+        MergeInfoVec { merge_info_vec }
+    }
+
+    pub fn file_cursor(&self) -> Result<Cursor<Vec<u8>>, std::io::Error> {
+        let mut w = csv::Writer::from_writer(Cursor::new(Vec::new()));
+        w.write_record([
+            "repo",
+            "branch",
+            "commit_id",
+            "mr_id",
+            "title",
+            "source_branch",
+            "target_branch",
+            "merger",
+            "merged_at",
+            "first_commit_at",
+            "commits",
+            "insertions",
+            "deletions",
+        ])
+        .unwrap();
+        for m in &self.merge_info_vec {
+            w.write_record(&[
+                m.repo.to_string(),
+                m.branch.to_string(),
+                m.commit_id.to_string(),
+                m.mr_id.to_string(),
+                m.title.to_string(),
+                m.source_branch.to_string(),
+                m.target_branch.to_string(),
+                m.merger.to_string(),
+                format_dt(&m.merged_at),
+                format_dt(&m.first_commit_at),
+                m.commits.to_string(),
+                m.insertions.to_string(),
+                m.deletions.to_string(),
+            ])
+            .unwrap();
+        }
+        let mut cursor = w.into_inner().unwrap();
+        cursor.flush()?;
+        Ok(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{write, Fixture};
+
+    #[test]
+    fn test_parse_gitlab() {
+        let m = parse_merge_message(
+            "Merge branch 'feature/login' into 'main'\n\nAdd login page\n\nCloses #7\n\nSee merge request group/web!123",
+        );
+        assert_eq!(m.id.as_deref(), Some("!123"));
+        assert_eq!(m.source_branch.as_deref(), Some("feature/login"));
+        assert_eq!(m.target_branch.as_deref(), Some("main"));
+        assert_eq!(m.title.as_deref(), Some("Add login page"));
+    }
+
+    #[test]
+    fn test_parse_github() {
+        let m =
+            parse_merge_message("Merge pull request #45 from alice/fix-typo\n\nFix typo in docs");
+        assert_eq!(m.id.as_deref(), Some("#45"));
+        assert_eq!(m.source_branch.as_deref(), Some("fix-typo"));
+        assert_eq!(m.target_branch, None);
+        assert_eq!(m.title.as_deref(), Some("Fix typo in docs"));
+    }
+
+    #[test]
+    fn test_parse_plain_merge() {
+        let m = parse_merge_message("Merge remote-tracking branch 'origin/release' into main");
+        assert_eq!(m.id, None);
+        assert_eq!(m.source_branch.as_deref(), Some("release"));
+        assert_eq!(m.target_branch.as_deref(), Some("main"));
+        assert_eq!(m.title, None);
+        assert_eq!(
+            parse_merge_message("Revert something"),
+            MergeMessage::default()
+        );
+    }
+
+    #[test]
+    fn test_merge_info() {
+        let fixture = Fixture::new();
+        let mut p = fixture.remote("p");
+        p.commit(
+            "alice",
+            "2024-01-01T10:00:00Z",
+            "init",
+            &[write("a.txt", "a\n")],
+        )
+        .branch("feat")
+        .commit(
+            "alice",
+            "2024-01-02T10:00:00Z",
+            "b",
+            &[write("b.txt", "b\n")],
+        )
+        .commit(
+            "alice",
+            "2024-01-03T10:00:00Z",
+            "c",
+            &[write("c.txt", "c\nc\n")],
+        )
+        .checkout("main")
+        .commit(
+            "alice",
+            "2024-01-02T12:00:00Z",
+            "d",
+            &[write("d.txt", "d\n")],
+        )
+        .merge(
+            "feat",
+            "alice",
+            "2024-01-04T10:00:00Z",
+            "Merge branch 'feat' into 'main'\n\nFeature\n\nSee merge request g/p!9",
+        );
+        let repo = p.repository();
+        let merge = repo.find_commit(p.head("main").unwrap()).unwrap();
+        let f2 = repo.find_commit(p.head("feat").unwrap()).unwrap();
+
+        let info = MergeInfo::from_commit(repo, &merge, "p", "main", &mut DiffOptions::new())
+            .unwrap()
+            .unwrap();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).ok();
+        assert_eq!(info.mr_id, "!9");
+        assert_eq!(info.commits, 2);
+        assert_eq!(info.insertions, 3);
+        assert_eq!(info.first_commit_at, at("2024-01-02T10:00:00Z"));
+        assert_eq!(info.merged_at, at("2024-01-04T10:00:00Z"));
+        assert!(
+            MergeInfo::from_commit(repo, &f2, "p", "main", &mut DiffOptions::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
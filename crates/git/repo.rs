@@ -73,7 +73,7 @@ fn do_fetch<'a>(
     }
 
     let fetch_head = repo.find_reference("FETCH_HEAD")?;
-    Ok(repo.reference_to_annotated_commit(&fetch_head)?)
+    repo.reference_to_annotated_commit(&fetch_head)
 }
 
 fn fast_forward(
@@ -177,7 +177,7 @@ fn do_merge<'a>(
     } else if analysis.0.is_normal() {
         // do a normal merge
         let head_commit = repo.reference_to_annotated_commit(&repo.head()?)?;
-        normal_merge(&repo, &head_commit, &fetch_commit)?;
+        normal_merge(repo, &head_commit, &fetch_commit)?;
     } else {
         println!("Nothing to do...");
     }
@@ -205,6 +205,6 @@ pub fn pull(
             None,
         )
         .unwrap();
-    let fetch_commit = do_fetch(&repo, &[remote_branch], &mut remote, username, password)?;
-    do_merge(&repo, &remote_branch, fetch_commit)
+    let fetch_commit = do_fetch(repo, &[remote_branch], &mut remote, username, password)?;
+    do_merge(repo, remote_branch, fetch_commit)
 }
@@ -0,0 +1,268 @@
+use config::SignatureOptions;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// 临时签名文件的序号，多线程验证同一个commit(多个分支)时不会互相覆盖
+static SIGNATURE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKind {
+    Gpg,
+    Ssh,
+    /// x509等不支持验证的签名
+    Other,
+}
+
+impl SignatureKind {
+    fn of(signature: &[u8]) -> Self {
+        if signature.starts_with(b"-----BEGIN PGP SIGNATURE-----") {
+            SignatureKind::Gpg
+        } else if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+            SignatureKind::Ssh
+        } else {
+            SignatureKind::Other
+        }
+    }
+}
+
+/// commit签名的检查结果，`valid` 为None表示没有签名或者没有配置对应的公钥无法验证
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SignatureStatus {
+    pub signed: bool,
+    pub valid: Option<bool>,
+}
+
+/// 离线验证commit签名，同git: ssh签名用`ssh-keygen -Y`和allowed signers文件，gpg签名用指定的keyring
+pub struct SignatureVerifier {
+    allowed_signers: Option<PathBuf>,
+    gpg_keyring: Option<PathBuf>,
+    /// 按commit id缓存结果，多个分支包含同一个commit时只验证一次
+    cache: Mutex<HashMap<String, SignatureStatus>>,
+}
+
+impl SignatureVerifier {
+    pub fn new(options: &SignatureOptions) -> Self {
+        // 子进程可能不在当前目录执行，转成绝对路径
+        let absolute = |p: &String| std::path::absolute(p).unwrap_or_else(|_| PathBuf::from(p));
+        SignatureVerifier {
+            allowed_signers: options.allowed_signers.as_ref().map(absolute),
+            gpg_keyring: options.gpg_keyring.as_ref().map(absolute),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `signature` 是backend提取的 (签名, 签名的数据)，没有签名时为None
+    pub fn verify(&self, id: &str, signature: Option<&(Vec<u8>, Vec<u8>)>) -> SignatureStatus {
+        let Some((signature, data)) = signature else {
+            return SignatureStatus::default();
+        };
+        if let Some(status) = self.cache.lock().unwrap().get(id) {
+            return *status;
+        }
+        let valid = match SignatureKind::of(signature) {
+            SignatureKind::Ssh => self
+                .allowed_signers
+                .as_deref()
+                .and_then(|f| verify_ssh(f, signature, data, id)),
+            SignatureKind::Gpg => self
+                .gpg_keyring
+                .as_deref()
+                .and_then(|f| verify_gpg(f, signature, data, id)),
+            SignatureKind::Other => None,
+        };
+        let status = SignatureStatus {
+            signed: true,
+            valid,
+        };
+        self.cache.lock().unwrap().insert(id.to_string(), status);
+        status
+    }
+}
+
+/// 签名写到临时文件，验证完删除
+fn with_signature_file<T>(signature: &[u8], id: &str, f: impl FnOnce(&Path) -> T) -> Option<T> {
+    let path = std::env::temp_dir().join(format!(
+        "git-stat-{}-{}-{}.sig",
+        std::process::id(),
+        SIGNATURE_FILES.fetch_add(1, Ordering::SeqCst),
+        id
+    ));
+    if let Err(e) = std::fs::write(&path, signature) {
+        warn!("write signature file failed: {} {}", path.display(), e);
+        return None;
+    }
+    let result = f(&path);
+    let _ = std::fs::remove_file(&path);
+    Some(result)
+}
+
+/// 执行命令，签名的数据从stdin输入，命令不存在时返回None
+fn run_with_stdin(cmd: &mut Command, data: &[u8]) -> Option<std::process::Output> {
+    let mut child = match cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("run {:?} failed: {}", cmd.get_program(), e);
+            return None;
+        }
+    };
+    // 命令可能提前退出，写入失败时以退出状态为准
+    let _ = child.stdin.take().unwrap().write_all(data);
+    child.wait_with_output().ok()
+}
+
+fn verify_ssh(allowed_signers: &Path, signature: &[u8], data: &[u8], id: &str) -> Option<bool> {
+    with_signature_file(signature, id, |sig| {
+        let output = Command::new("ssh-keygen")
+            .args(["-Y", "find-principals", "-f"])
+            .arg(allowed_signers)
+            .arg("-s")
+            .arg(sig)
+            .output();
+        let principal = match output {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(|s| s.to_string()),
+            Ok(_) => {
+                debug!("no principal found for ssh signature: {}", id);
+                return Some(false);
+            }
+            Err(e) => {
+                warn!("run ssh-keygen failed: {}", e);
+                return None;
+            }
+        };
+        let principal = principal?;
+        let mut cmd = Command::new("ssh-keygen");
+        cmd.args(["-Y", "verify", "-n", "git", "-f"])
+            .arg(allowed_signers)
+            .args(["-I", principal.as_str(), "-s"])
+            .arg(sig);
+        run_with_stdin(&mut cmd, data).map(|output| output.status.success())
+    })
+    .flatten()
+}
+
+fn verify_gpg(keyring: &Path, signature: &[u8], data: &[u8], id: &str) -> Option<bool> {
+    with_signature_file(signature, id, |sig| {
+        let mut cmd = Command::new("gpg");
+        cmd.args(["--batch", "--no-default-keyring", "--keyring"])
+            .arg(keyring)
+            .args(["--status-fd", "1", "--verify"])
+            .arg(sig)
+            .arg("-");
+        run_with_stdin(&mut cmd, data)
+            .map(|output| gpg_good_signature(&String::from_utf8_lossy(&output.stdout)))
+    })
+    .flatten()
+}
+
+/// `gpg --status-fd` 的输出中有 `GOODSIG` 即签名正确且公钥在keyring中
+fn gpg_good_signature(status: &str) -> bool {
+    status.lines().any(|l| l.starts_with("[GNUPG:] GOODSIG "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_kind() {
+        assert_eq!(
+            SignatureKind::of(b"-----BEGIN PGP SIGNATURE-----\n\niQ..."),
+            SignatureKind::Gpg
+        );
+        assert_eq!(
+            SignatureKind::of(b"-----BEGIN SSH SIGNATURE-----\nU1NI..."),
+            SignatureKind::Ssh
+        );
+        assert_eq!(
+            SignatureKind::of(b"-----BEGIN SIGNED MESSAGE-----"),
+            SignatureKind::Other
+        );
+    }
+
+    #[test]
+    fn test_gpg_good_signature() {
+        let good = "\
+[GNUPG:] NEWSIG
+[GNUPG:] KEY_CONSIDERED 6D7B1B5A0C2E9F3A4B8C1D2E3F4A5B6C7D8E9F0A 0
+[GNUPG:] SIG_ID 3u2CkB0xZ3tJ4cQ1Ff0Gm8s2b1Q 2024-01-01 1704067200
+[GNUPG:] GOODSIG 3F4A5B6C7D8E9F0A peter <peter@corp.com>
+[GNUPG:] VALIDSIG 6D7B1B5A0C2E9F3A4B8C1D2E3F4A5B6C7D8E9F0A 2024-01-01 1704067200 0 4 0 22 10 00 6D7B1B5A0C2E9F3A4B8C1D2E3F4A5B6C7D8E9F0A
+[GNUPG:] TRUST_UNDEFINED 0 pgp
+";
+        assert!(gpg_good_signature(good));
+        let bad = "\
+[GNUPG:] NEWSIG
+[GNUPG:] BADSIG 3F4A5B6C7D8E9F0A peter <peter@corp.com>
+";
+        assert!(!gpg_good_signature(bad));
+        let no_key = "\
+[GNUPG:] NEWSIG
+[GNUPG:] ERRSIG 3F4A5B6C7D8E9F0A 22 10 00 1704067200 9 -
+[GNUPG:] NO_PUBKEY 3F4A5B6C7D8E9F0A
+";
+        assert!(!gpg_good_signature(no_key));
+        assert!(!gpg_good_signature(""));
+    }
+
+    #[test]
+    fn test_verify_ssh() {
+        let fixture = crate::testing::Fixture::new();
+        let key = fixture.root().join("key");
+        let keygen = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "peter", "-f"])
+            .arg(&key)
+            .output();
+        // 需要OpenSSH 8.0+ 的 ssh-keygen，没有时失败而不是跳过
+        assert!(
+            keygen.is_ok_and(|o| o.status.success()),
+            "ssh-keygen is required to test ssh signature verification"
+        );
+        let data = b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\nsigned\n".to_vec();
+        let mut cmd = Command::new("ssh-keygen");
+        cmd.args(["-Y", "sign", "-n", "git", "-f"]).arg(&key);
+        let signature = run_with_stdin(&mut cmd, &data).unwrap().stdout;
+        let public_key = std::fs::read_to_string(key.with_extension("pub")).unwrap();
+        let allowed_signers = fixture.root().join("allowed_signers");
+        std::fs::write(&allowed_signers, format!("peter@corp.com {}", public_key)).unwrap();
+        let verifier = SignatureVerifier::new(&SignatureOptions {
+            allowed_signers: Some(allowed_signers.display().to_string()),
+            ..Default::default()
+        });
+
+        // 多个线程同时验证同一个commit
+        let signed = (signature, data);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| verifier.verify("c1", Some(&signed))))
+                .collect();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap().valid, Some(true));
+            }
+        });
+        let tampered = (signed.0.clone(), b"tampered".to_vec());
+        // 同一个commit id直接用缓存的结果
+        assert_eq!(verifier.verify("c1", Some(&tampered)).valid, Some(true));
+        let status = verifier.verify("c3", Some(&tampered));
+        assert_eq!(
+            status,
+            SignatureStatus {
+                signed: true,
+                valid: Some(false)
+            }
+        );
+        assert_eq!(verifier.verify("c2", None), SignatureStatus::default());
+    }
+}
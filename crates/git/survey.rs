@@ -0,0 +1,422 @@
+//! `git-stat init` 用的repo扫描：默认分支、主要语言、最近的作者
+use crate::commit::open_repo;
+use crate::loc::{count_tree, extensions_of};
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{Pathspec, Repository, Sort};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 不算作主要语言的文件类型
+const NON_CODE: &[&str] = &["Markdown", "JSON", "YAML", "TOML", "Other"];
+
+/// 顶层存在时建议排除的目录
+const VENDOR_DIRS: &[&str] = &["vendor", "node_modules", "third_party", "dist"];
+
+/// 扫描目录时不进入的目录
+const SKIP_DIRS: &[&str] = &["node_modules", "target", "vendor"];
+
+/// 一个作者身份，同一个人可能有多个
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    pub last_commit: DateTime<Utc>,
+}
+
+/// 一个repo的扫描结果
+#[derive(Debug, Clone)]
+pub struct RepoSurvey {
+    pub url: String,
+    /// 本地扫描到的路径，url为远程地址时用于注释
+    pub path: Option<PathBuf>,
+    pub default_branch: Option<String>,
+    /// 主要语言和代码行数，从多到少
+    pub languages: Vec<(String, usize)>,
+    /// 根据主要语言建议的pathspec，排除规则在前
+    pub pathspec: Vec<String>,
+    /// `since` 之后有commit的作者，commit数从多到少
+    pub identities: Vec<Identity>,
+}
+
+/// 可能是同一个人的多个身份
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorCluster {
+    /// commit最多的名字
+    pub name: String,
+    /// 所有出现过的名字，commit数从多到少
+    pub aliases: Vec<String>,
+    pub emails: Vec<String>,
+    pub commits: usize,
+}
+
+/// 找出目录下的git repo(包括bare repo)，最多 `max_depth` 层，不进入repo和隐藏目录
+pub fn find_repos(dir: &Path, max_depth: usize) -> Vec<PathBuf> {
+    if is_repo(dir) {
+        return vec![dir.to_path_buf()];
+    }
+    let mut found = vec![];
+    if max_depth == 0 {
+        return found;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return found;
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            !name.starts_with('.') && !SKIP_DIRS.contains(&name)
+        })
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        found.extend(find_repos(&dir, max_depth - 1));
+    }
+    found
+}
+
+fn is_repo(dir: &Path) -> bool {
+    dir.join(".git").exists() || (dir.join("HEAD").is_file() && dir.join("objects").is_dir())
+}
+
+/// 扫描本地repo，有origin时url用origin的地址
+pub fn survey_path(path: &Path, since: DateTime<Utc>) -> Result<RepoSurvey, git2::Error> {
+    let repo = Repository::open(path)?;
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let origin = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|r| r.url().map(String::from));
+    let url = origin.unwrap_or_else(|| path.to_string_lossy().to_string());
+    let mut survey = survey(&repo, url, since)?;
+    survey.path = Some(path);
+    Ok(survey)
+}
+
+/// clone远程repo到 `./repos` 后扫描
+pub fn survey_url(url: &str, since: DateTime<Utc>) -> Result<RepoSurvey, git2::Error> {
+    let repo = open_repo(&config::Repo::new(url, vec![]))?;
+    survey(&repo, url.to_string(), since)
+}
+
+fn survey(repo: &Repository, url: String, since: DateTime<Utc>) -> Result<RepoSurvey, git2::Error> {
+    let default_branch = default_branch(repo);
+    let head = match &default_branch {
+        Some(branch) => ["refs/remotes/origin/", "refs/heads/"]
+            .iter()
+            .find_map(|prefix| repo.find_reference(&format!("{}{}", prefix, branch)).ok())
+            .and_then(|r| r.peel_to_commit().ok()),
+        None => None,
+    };
+    let Some(head) = head else {
+        return Ok(RepoSurvey {
+            url,
+            path: None,
+            default_branch,
+            languages: vec![],
+            pathspec: vec![],
+            identities: vec![],
+        });
+    };
+
+    let tree = head.tree()?;
+    let counts = count_tree(repo, &tree, &Pathspec::new(Vec::<String>::new())?)?;
+    let mut languages: Vec<(String, usize)> = counts
+        .into_iter()
+        .filter(|(name, _)| !NON_CODE.contains(name))
+        .map(|(name, c)| (name.to_string(), c.code))
+        .filter(|(_, code)| *code > 0)
+        .collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let vendored: Vec<&str> = VENDOR_DIRS
+        .iter()
+        .copied()
+        .filter(|d| tree.get_name(d).is_some())
+        .collect();
+    let pathspec = suggest_pathspec(&languages, &vendored);
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME)?;
+    walk.push(head.id())?;
+    let mut identities: HashMap<(String, String), Identity> = HashMap::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let author = commit.author();
+        let time = Utc
+            .timestamp_opt(author.when().seconds(), 0)
+            .single()
+            .unwrap_or_default();
+        if time < since {
+            continue;
+        }
+        let name = author.name().unwrap_or("").to_string();
+        let email = author.email().unwrap_or("").to_string();
+        let identity = identities
+            .entry((name.clone(), email.clone()))
+            .or_insert(Identity {
+                name,
+                email,
+                commits: 0,
+                last_commit: time,
+            });
+        identity.commits += 1;
+        identity.last_commit = identity.last_commit.max(time);
+    }
+    Ok(RepoSurvey {
+        url,
+        path: None,
+        default_branch,
+        languages,
+        pathspec,
+        identities: sort_identities(identities.into_values().collect()),
+    })
+}
+
+fn sort_identities(mut identities: Vec<Identity>) -> Vec<Identity> {
+    identities.sort_by(|a, b| {
+        b.commits
+            .cmp(&a.commits)
+            .then(a.name.cmp(&b.name))
+            .then(a.email.cmp(&b.email))
+    });
+    identities
+}
+
+/// origin的HEAD，没有origin时用本地HEAD
+fn default_branch(repo: &Repository) -> Option<String> {
+    let target = repo
+        .find_reference("refs/remotes/origin/HEAD")
+        .ok()
+        .and_then(|r| r.symbolic_target().map(String::from))
+        .or_else(|| {
+            repo.find_reference("HEAD")
+                .ok()
+                .and_then(|r| r.symbolic_target().map(String::from))
+        })?;
+    ["refs/remotes/origin/", "refs/heads/"]
+        .iter()
+        .find_map(|prefix| target.strip_prefix(prefix))
+        .map(String::from)
+}
+
+/// 占代码行数10%以上的语言(最多3种)的扩展名，排除第三方代码目录；没有主要语言时不过滤
+pub fn suggest_pathspec(languages: &[(String, usize)], vendored: &[&str]) -> Vec<String> {
+    let total: usize = languages.iter().map(|(_, code)| code).sum();
+    let patterns: Vec<String> = languages
+        .iter()
+        .filter(|(_, code)| total > 0 && *code * 10 >= total)
+        .take(3)
+        .flat_map(|(name, _)| extensions_of(name).iter().map(|ext| format!("*.{}", ext)))
+        .collect();
+    if patterns.is_empty() {
+        return vec![];
+    }
+    // 排除规则要写在前面才生效
+    vendored
+        .iter()
+        .map(|d| format!("!{}", d))
+        .chain(patterns)
+        .collect()
+}
+
+/// 合并多个repo的作者身份
+pub fn merge_identities(surveys: &[RepoSurvey]) -> Vec<Identity> {
+    let mut merged: HashMap<(String, String), Identity> = HashMap::new();
+    for identity in surveys.iter().flat_map(|s| s.identities.iter()) {
+        merged
+            .entry((identity.name.clone(), identity.email.clone()))
+            .and_modify(|i| {
+                i.commits += identity.commits;
+                i.last_commit = i.last_commit.max(identity.last_commit);
+            })
+            .or_insert_with(|| identity.clone());
+    }
+    sort_identities(merged.into_values().collect())
+}
+
+/// 只保留字母数字并转小写，`Peter Chen`、`peter.chen`、`peterchen` 相同
+fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// email的用户名部分，去掉 `+tag` 和github noreply的数字前缀，太短或通用的用户名不用于匹配
+fn email_key(email: &str) -> Option<String> {
+    let (local, domain) = email.split_once('@')?;
+    let local = if domain.ends_with("users.noreply.github.com") {
+        local.split_once('+').map(|(_, user)| user).unwrap_or(local)
+    } else {
+        local.split('+').next().unwrap_or(local)
+    };
+    let key = name_key(local);
+    let generic = [
+        "root", "admin", "git", "noreply", "user", "dev", "ci", "bot",
+    ];
+    (key.len() >= 3 && !generic.contains(&key.as_str())).then_some(key)
+}
+
+/// 相同email、规范化后相同的名字、email用户名和名字相同的身份归为一个作者
+pub fn cluster_identities(identities: &[Identity]) -> Vec<AuthorCluster> {
+    let n = identities.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+    let keys: Vec<(String, String, Option<String>)> = identities
+        .iter()
+        .map(|i| {
+            (
+                i.email.to_lowercase(),
+                name_key(&i.name),
+                email_key(&i.email),
+            )
+        })
+        .collect();
+    for i in 0..n {
+        for j in i + 1..n {
+            let (a, b) = (&keys[i], &keys[j]);
+            let same = (!a.0.is_empty() && a.0 == b.0)
+                || (!a.1.is_empty() && a.1 == b.1)
+                || (a.2.is_some() && a.2 == b.2)
+                || a.2.as_deref() == Some(b.1.as_str())
+                || b.2.as_deref() == Some(a.1.as_str());
+            if same {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                parent[rj] = ri;
+            }
+        }
+    }
+
+    // identities已按commit数排序，每组第一个是commit最多的
+    let mut groups: Vec<(usize, AuthorCluster)> = vec![];
+    for (i, identity) in identities.iter().enumerate() {
+        let root = find(&mut parent, i);
+        let cluster = match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, cluster)) => cluster,
+            None => {
+                groups.push((
+                    root,
+                    AuthorCluster {
+                        name: identity.name.clone(),
+                        aliases: vec![],
+                        emails: vec![],
+                        commits: 0,
+                    },
+                ));
+                &mut groups.last_mut().unwrap().1
+            }
+        };
+        if !cluster.aliases.contains(&identity.name) {
+            cluster.aliases.push(identity.name.clone());
+        }
+        if !identity.email.is_empty() && !cluster.emails.contains(&identity.email) {
+            cluster.emails.push(identity.email.clone());
+        }
+        cluster.commits += identity.commits;
+    }
+    let mut clusters: Vec<AuthorCluster> = groups.into_iter().map(|(_, c)| c).collect();
+    clusters.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.name.cmp(&b.name)));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{write, Fixture};
+
+    fn identity(name: &str, email: &str, commits: usize) -> Identity {
+        Identity {
+            name: name.to_string(),
+            email: email.to_string(),
+            commits,
+            last_commit: DateTime::default(),
+        }
+    }
+
+    #[test]
+    fn test_cluster_identities() {
+        let identities = vec![
+            identity("Peter Chen", "peter@corp.com", 10),
+            identity("alice", "alice@corp.com", 8),
+            identity("peterchen", "pc@home.net", 5),
+            identity("pc", "pc@home.net", 3),
+            identity("Alice W", "12345+alice@users.noreply.github.com", 2),
+            identity("root", "root@localhost", 1),
+            identity("admin", "root@build", 1),
+        ];
+        let clusters = cluster_identities(&identities);
+        assert_eq!(clusters.len(), 4);
+        assert_eq!(clusters[0].name, "Peter Chen");
+        assert_eq!(clusters[0].aliases, vec!["Peter Chen", "peterchen", "pc"]);
+        assert_eq!(clusters[0].commits, 18);
+        assert_eq!(clusters[1].aliases, vec!["alice", "Alice W"]);
+        // 通用的email用户名不合并
+        assert_eq!(clusters[2].aliases, vec!["admin"]);
+        assert_eq!(clusters[3].aliases, vec!["root"]);
+    }
+
+    #[test]
+    fn test_suggest_pathspec() {
+        let languages = vec![
+            ("Go".to_string(), 900),
+            ("Shell".to_string(), 120),
+            ("Python".to_string(), 5),
+        ];
+        assert_eq!(
+            suggest_pathspec(&languages, &["vendor"]),
+            vec!["!vendor", "*.go", "*.sh", "*.bash", "*.zsh"]
+        );
+        assert!(suggest_pathspec(&[], &["vendor"]).is_empty());
+    }
+
+    #[test]
+    fn test_survey() {
+        let fixture = Fixture::new();
+        let mut api = fixture.remote("api");
+        api.commit(
+            "alice",
+            "2024-01-01T10:00:00Z",
+            "init",
+            &[
+                write("main.go", "package main\n\nfunc main() {}\n"),
+                write("vendor/x/x.go", "package x\n"),
+                write("README.md", "# api\n"),
+            ],
+        )
+        .branch("develop")
+        .commit(
+            "bob",
+            "2024-02-01T10:00:00Z",
+            "b",
+            &[write("b.go", "package main\n")],
+        )
+        .checkout("main")
+        .commit(
+            "alice",
+            "2024-03-01T10:00:00Z",
+            "a",
+            &[write("a.go", "package main\n")],
+        );
+        let since = "2024-01-15T00:00:00Z".parse().unwrap();
+        let found = find_repos(fixture.root(), 3);
+        assert_eq!(found, vec![fixture.root().join("remotes/api.git")]);
+
+        let survey = survey_path(&found[0], since).unwrap();
+        assert_eq!(survey.default_branch.as_deref(), Some("main"));
+        assert_eq!(survey.languages, vec![("Go".to_string(), 4)]);
+        assert_eq!(survey.pathspec, vec!["!vendor", "*.go"]);
+        assert_eq!(survey.identities.len(), 1);
+        assert_eq!(survey.identities[0].name, "alice");
+        assert_eq!(survey.identities[0].commits, 1);
+    }
+}
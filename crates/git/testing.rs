@@ -0,0 +1,382 @@
+//! 测试用的临时repo：在临时目录中按描述构造bare repo作为本地“远程”，clone、fetch、解析都可以离线测试
+//!
+//! 只在本crate的测试中编译，其他crate的测试需要开启 `testing` feature:
+//! `git = { path = "../git", features = ["testing"] }` 放在 `[dev-dependencies]`
+//!
+//! ```ignore
+//! use git::testing::{write, Fixture};
+//!
+//! let fixture = Fixture::new();
+//! let mut api = fixture.remote("api");
+//! api.commit("alice", "2024-01-01T10:00:00+08:00", "init", &[write("src/a.rs", "fn a() {}\n")])
+//!     .branch("feature")
+//!     .commit("bob", "2024-01-02T10:00:00+08:00", "b", &[write("src/b.rs", "fn b() {}\n")])
+//!     .checkout("main")
+//!     .merge("feature", "alice", "2024-01-03T10:00:00+08:00", "Merge branch 'feature'")
+//!     .tag("v1.0");
+//! let repo_conf = api.repo_conf(&["main"]);
+//! ```
+use chrono::DateTime;
+use git2::{Commit, IndexEntry, IndexTime, Oid, Repository, Signature, Time};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 一个commit中的文件变更
+#[derive(Debug, Clone)]
+pub enum Change {
+    Write(String, Vec<u8>),
+    Delete(String),
+    /// 内容不变移动到新路径
+    Rename(String, String),
+}
+
+pub fn write(path: &str, content: impl AsRef<[u8]>) -> Change {
+    Change::Write(path.to_string(), content.as_ref().to_vec())
+}
+
+pub fn delete(path: &str) -> Change {
+    Change::Delete(path.to_string())
+}
+
+pub fn rename(from: &str, to: &str) -> Change {
+    Change::Rename(from.to_string(), to.to_string())
+}
+
+/// 临时目录，drop时删除。`remotes/` 下是bare repo，`repos/` 是clone的目录
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "git-stat-fixture-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        Fixture { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// clone的目录，传给 `repo_parse_in`
+    pub fn repos_dir(&self) -> PathBuf {
+        self.root.join("repos")
+    }
+
+    /// 新建bare repo `remotes/<name>.git`，默认分支main
+    pub fn remote(&self, name: &str) -> RemoteBuilder {
+        let path = self.root.join("remotes").join(format!("{}.git", name));
+        let repo = Repository::init_bare(&path).unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        RemoteBuilder {
+            repo,
+            path,
+            branch: "main".to_string(),
+        }
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// 在当前分支上追加commit、建分支、合并、打tag，都直接写到bare repo
+pub struct RemoteBuilder {
+    repo: Repository,
+    path: PathBuf,
+    branch: String,
+}
+
+/// 作者名，email是 `<name>@example.com`；时间是RFC3339
+fn signature(author: &str, time: &str) -> Signature<'static> {
+    let time = DateTime::parse_from_rfc3339(time)
+        .unwrap_or_else(|e| panic!("invalid fixture time {}: {}", time, e));
+    let offset = time.offset().local_minus_utc() / 60;
+    Signature::new(
+        author,
+        &format!("{}@example.com", author),
+        &Time::new(time.timestamp(), offset),
+    )
+    .unwrap()
+}
+
+fn index_entry(path: &str, id: Oid, size: usize) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+impl RemoteBuilder {
+    /// 作为url clone
+    pub fn url(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    pub fn repository(&self) -> &Repository {
+        &self.repo
+    }
+
+    /// 指向这个remote的repo配置，repo名是 `<name>`
+    pub fn repo_conf(&self, branches: &[&str]) -> config::Repo {
+        config::Repo::new(
+            &self.url(),
+            branches.iter().map(|b| b.to_string()).collect(),
+        )
+    }
+
+    /// 分支的head，分支还没有commit时为None
+    pub fn head(&self, branch: &str) -> Option<Oid> {
+        self.repo
+            .find_reference(&format!("refs/heads/{}", branch))
+            .ok()
+            .and_then(|r| r.target())
+    }
+
+    fn find_commit(&self, branch: &str) -> Option<Commit<'_>> {
+        self.head(branch)
+            .map(|oid| self.repo.find_commit(oid).unwrap())
+    }
+
+    fn write_commit(
+        &self,
+        author: &str,
+        time: &str,
+        message: &str,
+        tree: Oid,
+        parents: &[&Commit],
+        gpgsig: Option<&str>,
+    ) -> Oid {
+        let sig = signature(author, time);
+        let tree = self.repo.find_tree(tree).unwrap();
+        let refname = format!("refs/heads/{}", self.branch);
+        let Some(gpgsig) = gpgsig else {
+            return self
+                .repo
+                .commit(Some(&refname), &sig, &sig, message, &tree, parents)
+                .unwrap();
+        };
+        let buf = self
+            .repo
+            .commit_create_buffer(&sig, &sig, message, &tree, parents)
+            .unwrap();
+        let oid = self
+            .repo
+            .commit_signed(buf.as_str().unwrap(), gpgsig, None)
+            .unwrap();
+        self.repo
+            .reference(&refname, oid, true, "fixture signed commit")
+            .unwrap();
+        oid
+    }
+
+    /// 在当前分支上提交，author同时是committer
+    pub fn commit(
+        &mut self,
+        author: &str,
+        time: &str,
+        message: &str,
+        changes: &[Change],
+    ) -> &mut Self {
+        self.commit_tree(author, time, message, changes, None);
+        self
+    }
+
+    /// 带 `gpgsig` 头的commit，签名内容原样写入，不做校验
+    pub fn signed_commit(
+        &mut self,
+        author: &str,
+        time: &str,
+        message: &str,
+        changes: &[Change],
+        gpgsig: &str,
+    ) -> &mut Self {
+        self.commit_tree(author, time, message, changes, Some(gpgsig));
+        self
+    }
+
+    fn commit_tree(
+        &self,
+        author: &str,
+        time: &str,
+        message: &str,
+        changes: &[Change],
+        gpgsig: Option<&str>,
+    ) {
+        let parent = self.find_commit(&self.branch);
+        let mut index = git2::Index::new().unwrap();
+        if let Some(parent) = &parent {
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+        for change in changes {
+            match change {
+                Change::Write(path, content) => {
+                    let id = self.repo.blob(content).unwrap();
+                    index.add(&index_entry(path, id, content.len())).unwrap();
+                }
+                Change::Delete(path) => index.remove_path(Path::new(path)).unwrap(),
+                Change::Rename(from, to) => {
+                    let entry = index
+                        .get_path(Path::new(from), 0)
+                        .unwrap_or_else(|| panic!("rename: {} not found", from));
+                    index.remove_path(Path::new(from)).unwrap();
+                    index
+                        .add(&index_entry(to, entry.id, entry.file_size as usize))
+                        .unwrap();
+                }
+            }
+        }
+        let tree = index.write_tree_to(&self.repo).unwrap();
+        let parents: Vec<&Commit> = parent.iter().collect();
+        self.write_commit(author, time, message, tree, &parents, gpgsig);
+    }
+
+    /// 从当前分支的head新建分支并切换过去
+    pub fn branch(&mut self, name: &str) -> &mut Self {
+        let head = self
+            .head(&self.branch)
+            .unwrap_or_else(|| panic!("branch {} has no commit", self.branch));
+        self.repo
+            .reference(
+                &format!("refs/heads/{}", name),
+                head,
+                false,
+                "fixture branch",
+            )
+            .unwrap();
+        self.branch = name.to_string();
+        self
+    }
+
+    /// 切换当前分支，之后的commit在这个分支上
+    pub fn checkout(&mut self, name: &str) -> &mut Self {
+        self.branch = name.to_string();
+        self
+    }
+
+    /// 把分支合并到当前分支，生成两个parent的merge commit，有冲突时panic
+    pub fn merge(&mut self, from: &str, author: &str, time: &str, message: &str) -> &mut Self {
+        self.merge_commit(from, author, time, message);
+        self
+    }
+
+    fn merge_commit(&self, from: &str, author: &str, time: &str, message: &str) {
+        let ours = self.find_commit(&self.branch).unwrap();
+        let theirs = self
+            .find_commit(from)
+            .unwrap_or_else(|| panic!("branch {} not found", from));
+        let mut index = self.repo.merge_commits(&ours, &theirs, None).unwrap();
+        assert!(!index.has_conflicts(), "merge {} has conflicts", from);
+        let tree = index.write_tree_to(&self.repo).unwrap();
+        self.write_commit(author, time, message, tree, &[&ours, &theirs], None);
+    }
+
+    /// 在当前分支head打轻量tag
+    pub fn tag(&mut self, name: &str) -> &mut Self {
+        let head = self.head(&self.branch).unwrap();
+        {
+            let object = self.repo.find_object(head, None).unwrap();
+            self.repo.tag_lightweight(name, &object, false).unwrap();
+        }
+        self
+    }
+
+    /// 在当前分支head打附注tag
+    pub fn annotated_tag(
+        &mut self,
+        name: &str,
+        tagger: &str,
+        time: &str,
+        message: &str,
+    ) -> &mut Self {
+        let head = self.head(&self.branch).unwrap();
+        {
+            let object = self.repo.find_object(head, None).unwrap();
+            self.repo
+                .tag(name, &object, &signature(tagger, time), message, false)
+                .unwrap();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture() {
+        let fixture = Fixture::new();
+        let mut api = fixture.remote("api");
+        api.commit(
+            "alice",
+            "2024-01-01T10:00:00+08:00",
+            "init",
+            &[write("src/a.rs", "a\n"), write("README.md", "# api\n")],
+        )
+        .branch("feature")
+        .commit(
+            "bob",
+            "2024-01-02T10:00:00-05:00",
+            "move",
+            &[rename("src/a.rs", "src/b.rs"), delete("README.md")],
+        )
+        .checkout("main")
+        .commit(
+            "alice",
+            "2024-01-03T10:00:00Z",
+            "docs",
+            &[write("docs/x.md", "x\n")],
+        )
+        .merge(
+            "feature",
+            "alice",
+            "2024-01-04T10:00:00Z",
+            "Merge branch 'feature'",
+        )
+        .annotated_tag("v1.0", "alice", "2024-01-04T11:00:00Z", "release");
+
+        let repo = api.repository();
+        let head = repo.find_commit(api.head("main").unwrap()).unwrap();
+        assert_eq!(head.parent_count(), 2);
+        let tree = head.tree().unwrap();
+        let paths: Vec<String> = tree.iter().map(|e| e.name().unwrap().to_string()).collect();
+        assert_eq!(paths, vec!["docs", "src"]);
+        assert!(tree.get_path(Path::new("src/b.rs")).is_ok());
+
+        let feature = repo.find_commit(api.head("feature").unwrap()).unwrap();
+        assert_eq!(feature.author().name(), Some("bob"));
+        assert_eq!(feature.author().when().offset_minutes(), -300);
+        let tag = repo.revparse_single("v1.0").unwrap();
+        assert_eq!(tag.peel_to_commit().unwrap().id(), head.id());
+
+        let root = fixture.root().to_path_buf();
+        drop(fixture);
+        assert!(!root.exists());
+    }
+}
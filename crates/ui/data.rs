@@ -68,3 +68,48 @@ impl Data {
         &self.deletions
     }
 }
+
+/// 通用表格，任意DataFrame转成header + rows交给TUI渲染
+#[derive(Debug, Default, Clone)]
+pub struct Sheet {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// 数值单元格按大小着色，用于热力图
+    pub heatmap: bool,
+}
+
+impl Sheet {
+    pub fn new(header: Vec<String>, rows: Vec<Vec<String>>) -> Self {
+        Sheet {
+            header,
+            rows,
+            heatmap: false,
+        }
+    }
+
+    pub fn with_heatmap(mut self, heatmap: bool) -> Self {
+        self.heatmap = heatmap;
+        self
+    }
+}
+
+impl From<Vec<Data>> for Sheet {
+    fn from(data_vec: Vec<Data>) -> Self {
+        let header = [
+            "repo",
+            "date",
+            "branch",
+            "author",
+            "insertions",
+            "deletions",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let rows = data_vec
+            .iter()
+            .map(|d| d.ref_array().iter().map(|s| s.to_string()).collect())
+            .collect();
+        Sheet::new(header, rows)
+    }
+}
@@ -1,4 +1,4 @@
-use data::Data;
+use data::Sheet;
 use std::{error::Error, io};
 
 use crate::data;
@@ -31,6 +31,8 @@ const INFO_TEXT: &str =
     "(Esc) quit | (↑) move up | (↓) move down | (→) next color | (←) previous color";
 
 const ITEM_HEIGHT: usize = 4;
+// 热力图一行一个格子，不留上下空行
+const HEATMAP_ITEM_HEIGHT: usize = 1;
 
 struct TableColors {
     buffer_bg: Color,
@@ -60,25 +62,57 @@ impl TableColors {
 
 struct App {
     state: TableState,
-    items: Vec<Data>,
-    longest_item_lens: (u16, u16, u16, u16, u16, u16),
+    header: Vec<String>,
+    items: Vec<Vec<String>>,
+    longest_item_lens: Vec<u16>,
     scroll_state: ScrollbarState,
     colors: TableColors,
     color_index: usize,
+    item_height: usize,
+    /// 热力图模式下小时单元格的最大值
+    heat_max: Option<f64>,
+    /// 每列是否是热力图的小时列
+    heat_columns: Vec<bool>,
 }
 
 impl App {
-    fn new(data_vec: Vec<Data>) -> Self {
+    fn new(sheet: Sheet) -> Self {
+        let item_height = if sheet.heatmap {
+            HEATMAP_ITEM_HEIGHT
+        } else {
+            ITEM_HEIGHT
+        };
+        let heat_columns: Vec<bool> = sheet
+            .header
+            .iter()
+            .map(|h| sheet.heatmap && is_hour_column(h))
+            .collect();
+        let heat_max = if sheet.heatmap {
+            Some(heat_max(&heat_columns, &sheet.rows))
+        } else {
+            None
+        };
         Self {
             state: TableState::default().with_selected(0),
-            longest_item_lens: constraint_len_calculator(&data_vec),
-            scroll_state: ScrollbarState::new((data_vec.len() - 1) * ITEM_HEIGHT),
+            longest_item_lens: constraint_len_calculator(&sheet.header, &sheet.rows)
+                .into_iter()
+                .zip(&sheet.header)
+                .map(|(len, h)| len.max(h.width() as u16))
+                .collect(),
+            scroll_state: ScrollbarState::new(sheet.rows.len().saturating_sub(1) * item_height),
             colors: TableColors::new(&PALETTES[0]),
             color_index: 0,
-            items: data_vec,
+            header: sheet.header,
+            items: sheet.rows,
+            item_height,
+            heat_max,
+            heat_columns,
         }
     }
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -90,10 +124,13 @@ impl App {
             None => 0,
         };
         self.state.select(Some(i));
-        self.scroll_state = self.scroll_state.position(i * ITEM_HEIGHT);
+        self.scroll_state = self.scroll_state.position(i * self.item_height);
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -105,7 +142,7 @@ impl App {
             None => 0,
         };
         self.state.select(Some(i));
-        self.scroll_state = self.scroll_state.position(i * ITEM_HEIGHT);
+        self.scroll_state = self.scroll_state.position(i * self.item_height);
     }
 
     pub fn next_color(&mut self) {
@@ -120,9 +157,29 @@ impl App {
     pub fn set_colors(&mut self) {
         self.colors = TableColors::new(&PALETTES[self.color_index]);
     }
+
+    /// 按数值占最大值的比例取当前调色板的深浅
+    fn heat_color(&self, content: &str) -> Option<Color> {
+        let max = self.heat_max?;
+        let value = content.parse::<f64>().ok()?;
+        if value <= 0.0 || max <= 0.0 {
+            return None;
+        }
+        let palette = &PALETTES[self.color_index];
+        let shades = [
+            palette.c900,
+            palette.c800,
+            palette.c700,
+            palette.c600,
+            palette.c500,
+            palette.c400,
+        ];
+        let idx = ((value / max) * (shades.len() - 1) as f64).round() as usize;
+        Some(shades[idx.min(shades.len() - 1)])
+    }
 }
 
-pub fn run(data: Vec<Data>) -> Result<(), Box<dyn Error>> {
+pub fn run(sheet: Sheet) -> Result<(), Box<dyn Error>> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -131,7 +188,7 @@ pub fn run(data: Vec<Data>) -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new(data);
+    let app = App::new(sheet);
     let res = run_app(&mut terminal, app);
 
     // restore terminal
@@ -189,105 +246,87 @@ fn render_table(f: &mut Frame, app: &mut App, area: Rect) {
         .add_modifier(Modifier::REVERSED)
         .fg(app.colors.selected_style_fg);
 
-    let header = [
-        "repo",
-        "date",
-        "branch",
-        "author",
-        "insertions",
-        "deletions",
-    ]
-    .into_iter()
-    .map(Cell::from)
-    .collect::<Row>()
-    .style(header_style)
-    .height(1);
-    let rows = app.items.iter().enumerate().map(|(i, data)| {
+    let header = app
+        .header
+        .iter()
+        .map(|h| Cell::from(h.as_str()))
+        .collect::<Row>()
+        .style(header_style)
+        .height(1);
+    let rows = app.items.iter().enumerate().map(|(i, item)| {
         let color = match i % 2 {
             0 => app.colors.normal_row_color,
             _ => app.colors.alt_row_color,
         };
-        let item = data.ref_array();
-        item.into_iter()
-            .map(|content| Cell::from(Text::from(format!("\n{content}\n"))))
+        item.iter()
+            .enumerate()
+            .map(|(j, content)| {
+                if app.heat_max.is_some() {
+                    let cell = Cell::from(Text::from(content.as_str()));
+                    let heat = app.heat_columns.get(j) == Some(&true);
+                    match app.heat_color(content).filter(|_| heat) {
+                        Some(heat) => cell.style(Style::new().bg(heat)),
+                        None => cell,
+                    }
+                } else {
+                    Cell::from(Text::from(format!("\n{content}\n")))
+                }
+            })
             .collect::<Row>()
             .style(Style::new().fg(app.colors.row_fg).bg(color))
-            .height(4)
+            .height(app.item_height as u16)
     });
     let bar = " █ ";
-    let t = Table::new(
-        rows,
-        [
-            // + 1 is for padding.
-            Constraint::Length(app.longest_item_lens.0),
-            Constraint::Min(app.longest_item_lens.1 + 1),
-            Constraint::Min(app.longest_item_lens.2),
-            Constraint::Min(app.longest_item_lens.3),
-            Constraint::Min(app.longest_item_lens.4),
-            Constraint::Min(app.longest_item_lens.5),
-        ],
-    )
-    .header(header)
-    .highlight_style(selected_style)
-    .highlight_symbol(Text::from(vec![
-        "".into(),
-        bar.into(),
-        bar.into(),
-        "".into(),
-    ]))
-    .bg(app.colors.buffer_bg)
-    .highlight_spacing(HighlightSpacing::Always);
+    // + 1 is for padding.
+    let widths = app
+        .longest_item_lens
+        .iter()
+        .map(|len| Constraint::Min(len + 1))
+        .collect::<Vec<_>>();
+    let highlight_symbol = if app.item_height == ITEM_HEIGHT {
+        Text::from(vec!["".into(), bar.into(), bar.into(), "".into()])
+    } else {
+        Text::from(bar)
+    };
+    let t = Table::new(rows, widths)
+        .header(header)
+        .highlight_style(selected_style)
+        .highlight_symbol(highlight_symbol)
+        .bg(app.colors.buffer_bg)
+        .highlight_spacing(HighlightSpacing::Always);
     f.render_stateful_widget(t, area, &mut app.state);
 }
 
-fn constraint_len_calculator(items: &[Data]) -> (u16, u16, u16, u16, u16, u16) {
-    let repo_len = items
-        .iter()
-        .map(Data::repo)
-        .map(UnicodeWidthStr::width)
-        .max()
-        .unwrap_or(0);
-    let date_len = items
-        .iter()
-        .map(Data::date)
-        .map(UnicodeWidthStr::width)
-        .max()
-        .unwrap_or(0);
-    let branch_len = items
-        .iter()
-        .map(Data::branch)
-        .flat_map(str::lines)
-        .map(UnicodeWidthStr::width)
-        .max()
-        .unwrap_or(0);
-    let author_len = items
-        .iter()
-        .map(Data::author)
-        .map(UnicodeWidthStr::width)
-        .max()
-        .unwrap_or(0);
-    let insertions_len = items
-        .iter()
-        .map(Data::insertions)
-        .map(UnicodeWidthStr::width)
-        .max()
-        .unwrap_or(0);
-    let deletions_len = items
+/// 每列内容的最大宽度，多行内容按行计算
+fn constraint_len_calculator(header: &[String], items: &[Vec<String>]) -> Vec<u16> {
+    (0..header.len())
+        .map(|i| {
+            let len = items
+                .iter()
+                .filter_map(|row| row.get(i))
+                .flat_map(|s| s.lines())
+                .map(UnicodeWidthStr::width)
+                .max()
+                .unwrap_or(0);
+            #[allow(clippy::cast_possible_truncation)]
+            let len = len as u16;
+            len
+        })
+        .collect()
+}
+
+/// 热力图的小时列 `00`-`23`，不包括作者、星期和total列
+fn is_hour_column(header: &str) -> bool {
+    header.len() == 2 && header.parse::<u8>().is_ok_and(|h| h < 24)
+}
+
+/// 热力图中所有小时单元格的最大值
+fn heat_max(heat_columns: &[bool], items: &[Vec<String>]) -> f64 {
+    items
         .iter()
-        .map(Data::deletions)
-        .map(UnicodeWidthStr::width)
-        .max()
-        .unwrap_or(0);
-
-    #[allow(clippy::cast_possible_truncation)]
-    (
-        repo_len as u16,
-        date_len as u16,
-        branch_len as u16,
-        author_len as u16,
-        insertions_len as u16,
-        deletions_len as u16,
-    )
+        .flat_map(|row| row.iter().zip(heat_columns).filter(|(_, heat)| **heat))
+        .filter_map(|(s, _)| s.parse::<f64>().ok())
+        .fold(0.0, f64::max)
 }
 
 fn render_scrollbar(f: &mut Frame, app: &mut App, area: Rect) {
@@ -318,7 +357,7 @@ fn render_footer(f: &mut Frame, app: &App, area: Rect) {
 
 #[cfg(test)]
 mod tests {
-    use crate::data::Data;
+    use crate::data::{Data, Sheet};
 
     #[test]
     fn constraint_len_calculator() {
@@ -340,6 +379,8 @@ mod tests {
                 deletions: "235".to_string(),
             },
         ];
+        let sheet = Sheet::from(test_data);
+        let lens = crate::tui::constraint_len_calculator(&sheet.header, &sheet.rows);
         let (
             longest_repo_len,
             longest_date_len,
@@ -347,7 +388,7 @@ mod tests {
             longest_author_len,
             longest_insertions_len,
             longest_deletions_len,
-        ) = crate::tui::constraint_len_calculator(&test_data);
+        ) = (lens[0], lens[1], lens[2], lens[3], lens[4], lens[5]);
 
         assert_eq!(15, longest_repo_len);
         assert_eq!(19, longest_date_len);
@@ -356,4 +397,16 @@ mod tests {
         assert_eq!(4, longest_insertions_len);
         assert_eq!(3, longest_deletions_len);
     }
+
+    #[test]
+    fn heat_max() {
+        let header = ["author", "weekday", "09", "10", "total"].map(String::from);
+        let heat_columns: Vec<bool> = header.iter().map(|h| super::is_hour_column(h)).collect();
+        assert_eq!(vec![false, false, true, true, false], heat_columns);
+        let rows = vec![
+            ["42", "Mon", "3", "0", "3"].map(String::from).to_vec(),
+            ["peter", "Tue", "12", "4", "16"].map(String::from).to_vec(),
+        ];
+        assert_eq!(12.0, crate::tui::heat_max(&heat_columns, &rows));
+    }
 }